@@ -28,6 +28,10 @@ pub struct Sprites {
 
 #[derive(geng::asset::Load)]
 pub struct Assets {
+    #[load(serde, path = "config.ron")]
+    pub config: crate::model::Config,
+    #[load(serde, path = "chunks.ron")]
+    pub chunks: Vec<crate::model::Chunk>,
     pub end: ugli::Texture,
     pub sprites: Sprites,
     #[load(ext = "mp3", options(looped = "true"))]
@@ -37,9 +41,12 @@ pub struct Assets {
 
 impl Assets {
     pub async fn load(manager: &geng::asset::Manager) -> anyhow::Result<Self> {
-        geng::asset::Load::load(manager, &run_dir().join("assets"), &())
+        let assets: Self = geng::asset::Load::load(manager, &run_dir().join("assets"), &())
             .await
-            .context("failed to load assets")
+            .context("failed to load assets")?;
+        assets.config.validate().context("invalid config.ron")?;
+        crate::model::validate_chunks(&assets.chunks).context("invalid chunks.ron")?;
+        Ok(assets)
     }
 }
 
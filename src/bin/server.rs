@@ -0,0 +1,59 @@
+//! Relay server for the online versus mode.
+//!
+//! Waits for the players to connect, sends them the same seed,
+//! and then forwards each player's state to the others as a ghost.
+//! Once everyone has been eliminated, sends the final standings.
+
+#[path = "../net/protocol.rs"]
+mod protocol;
+#[path = "../net/relay.rs"]
+mod relay;
+
+use geng::prelude::*;
+use protocol::*;
+
+use std::net::TcpListener;
+
+#[derive(clap::Parser)]
+struct Opts {
+    /// Address to listen on.
+    #[clap(long, default_value = DEFAULT_ADDR)]
+    addr: String,
+    /// Number of players in each race.
+    #[clap(long, default_value_t = 2)]
+    players: usize,
+}
+
+fn main() -> anyhow::Result<()> {
+    logger::init();
+    let opts: Opts = clap::Parser::parse();
+    anyhow::ensure!(opts.players >= 1, "there must be at least one player");
+
+    let listener = TcpListener::bind(&opts.addr)
+        .with_context(|| format!("failed to listen on {:?}", opts.addr))?;
+    log::info!("Listening on {}", opts.addr);
+
+    // Gather players into races, one race at a time
+    let mut waiting = Vec::new();
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                log::error!("Failed to accept a connection: {err}");
+                continue;
+            }
+        };
+        log::info!("Player connected from {:?}", stream.peer_addr());
+        stream.set_nodelay(true)?;
+        waiting.push(stream);
+        if waiting.len() == opts.players {
+            let streams = std::mem::take(&mut waiting);
+            std::thread::spawn(move || {
+                if let Err(err) = relay::run_race(streams) {
+                    log::error!("Race failed: {err:?}");
+                }
+            });
+        }
+    }
+    Ok(())
+}
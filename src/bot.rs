@@ -0,0 +1,187 @@
+use crate::prelude::*;
+
+/// How far a bird can be to get shot at.
+const SHOOT_DISTANCE: f32 = 6.0;
+/// Extra preference for clouds with a spring, in units of height.
+const SPRING_BONUS: f32 = 2.0;
+
+/// An autopilot that plays the game by producing `PlayerInput`s from the `Model`.
+pub struct Bot {
+    /// The cloud the bot is trying to land on next.
+    target: Option<Id>,
+}
+
+impl Default for Bot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bot {
+    pub fn new() -> Self {
+        Self { target: None }
+    }
+
+    /// Decide the input of the `player` for the next tick.
+    pub fn input(&mut self, model: &Model, player: usize) -> PlayerInput {
+        let mut input = PlayerInput {
+            input_dir: vec2::ZERO,
+            jump: false,
+            shoot: false,
+            aim: Aim::Direction(vec2::UNIT_Y),
+        };
+        let Some(doodle) = model.players[player].body else {
+            return input;
+        };
+        let (&position, &grounded) =
+            get!(model.doodles, doodle, (&body.collider.position, &grounded)).unwrap();
+
+        // Pick a new target once landed, or if the old one has despawned
+        let target_valid = self.target.map_or(false, |target| {
+            get!(model.clouds, target, (&body.collider.position)).is_some()
+        });
+        if grounded.is_some() || !target_valid {
+            self.target = self.pick_target(model, doodle, grounded);
+        }
+
+        if let Some(target) = self.target {
+            let (&cloud_pos,) = get!(model.clouds, target, (&body.collider.position)).unwrap();
+            let delta = position.delta_to(cloud_pos);
+            // Steer towards the cloud, slowing down when close
+            input.input_dir.x = (delta.x / r32(0.5)).clamp_abs(Coord::ONE);
+            input.jump = grounded.is_some() && grounded != Some(target);
+        } else if let Some(cloud) = grounded {
+            // Nowhere to go, try to get higher by just jumping in place
+            let (&cloud_pos,) = get!(model.clouds, cloud, (&body.collider.position)).unwrap();
+            input.input_dir.x = (position.delta_to(cloud_pos).x / r32(0.5)).clamp_abs(Coord::ONE);
+            input.jump = true;
+        }
+
+        // Shoot the closest bird flying towards us
+        let projectile_speed = model.config.doodle.projectile_speed;
+        let threat = query!(model.birds, (&body.collider.position, &body.velocity))
+            .map(|(id, (&bird_pos, &bird_vel))| (id, position.delta_to(bird_pos), bird_vel))
+            .filter(|(_, delta, bird_vel)| {
+                delta.len().as_f32() < SHOOT_DISTANCE && vec2::dot(*delta, *bird_vel) < Coord::ZERO
+            })
+            .min_by_key(|(_, delta, _)| delta.len());
+        if let Some((bird, delta, bird_vel)) = threat {
+            // Lead the target
+            let time = delta.len() / projectile_speed;
+            let aim = (delta + bird_vel * time).normalize_or_zero();
+            // Only shoot if the projectile hits the bird, moving along with the bird
+            let relative = aim * projectile_speed - bird_vel;
+            let hit = model.shape_cast(
+                &Shape::circle(0.2),
+                position,
+                relative,
+                relative.len() * time * r32(2.0),
+                QueryFilter::only(&[EntityKind::Bird]),
+            );
+            input.shoot = hit.map_or(false, |hit| hit.id == bird);
+            input.aim = Aim::Direction(aim);
+        }
+
+        input
+    }
+
+    /// Choose the highest cloud that can be reached with a single jump,
+    /// preferring the ones with a spring and the ones close horizontally.
+    fn pick_target(&self, model: &Model, doodle: Id, grounded: Option<Id>) -> Option<Id> {
+        let (collider,) = get!(model.doodles, doodle, (&body.collider)).unwrap();
+        let position = collider.position;
+        let doodle_width = collider.clone().compute_aabb().width();
+
+        let springs: Vec<Id> = query!(model.triggers, (&kind, &attached_to))
+            .filter(|(_, (kind, _))| matches!(kind, TriggerKind::Spring))
+            .filter_map(|(_, (_, attachment))| attachment.as_ref().map(|a| a.cloud))
+            .collect();
+        let on_spring = grounded.map_or(false, |cloud| springs.contains(&cloud));
+
+        query!(model.clouds, (&body.collider))
+            .filter(|&(id, _)| Some(id) != grounded)
+            .filter_map(|(id, (cloud_collider,))| {
+                let cloud_pos = cloud_collider.position;
+                let cloud_width = cloud_collider.clone().compute_aabb().width();
+                let landing_width = (doodle_width + cloud_width) / r32(2.0);
+                let mut arc = JumpArc::new(&model.config, model.world_width, landing_width);
+                if on_spring {
+                    arc = arc.spring(&model.config);
+                }
+                // Only whether the cloud is in reach right now matters,
+                // the bot picks a new target after landing anyway
+                let delta = position.delta_to(cloud_pos);
+                let reachable = delta.y > r32(0.3)
+                    && arc.can_reach(position, vec2::ZERO, cloud_pos, vec2::ZERO);
+                let bonus = if springs.contains(&id) {
+                    SPRING_BONUS
+                } else {
+                    0.0
+                };
+                let delta = delta.as_f32();
+                reachable.then(|| (id, r32(delta.y + bonus - delta.x.abs() * 0.2)))
+            })
+            .max_by_key(|&(_, value)| value)
+            .map(|(id, _)| id)
+    }
+}
+
+/// Results of the headless games played by the bot.
+#[derive(Debug, PartialEq)]
+pub struct SimulationStats {
+    pub scores: Vec<i32>,
+    pub heights: Vec<f32>,
+}
+
+/// Play `games` games with the bot without rendering anything,
+/// each limited to `max_time` seconds of game time.
+pub fn simulate(
+    config: &Config,
+    chunks: &[Chunk],
+    seed: u64,
+    games: usize,
+    max_time: f64,
+) -> SimulationStats {
+    let delta_time = Time::new(crate::game::TICKS_PER_SECOND.recip() as _);
+    let max_ticks = (max_time * crate::game::TICKS_PER_SECOND) as usize;
+    let mut stats = SimulationStats {
+        scores: Vec::with_capacity(games),
+        heights: Vec::with_capacity(games),
+    };
+    for game in 0..games {
+        let mut model = Model::new(
+            config.clone(),
+            chunks.to_vec(),
+            seed.wrapping_add(game as u64),
+            1,
+        );
+        let mut bot = Bot::new();
+        for _ in 0..max_ticks {
+            let input = bot.input(&model, 0);
+            model.update(&[input], delta_time);
+            if model.is_game_over() {
+                break;
+            }
+        }
+        let player = &model.players[0];
+        stats.scores.push(player.total_score());
+        stats.heights.push(player.stats.max_height.as_f32());
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulation_is_reproducible() {
+        let config = ron::from_str(include_str!("../assets/config.ron")).unwrap();
+        let chunks: Vec<Chunk> = ron::from_str(include_str!("../assets/chunks.ron")).unwrap();
+        // The seeds of the later games wrap around
+        let seed = u64::MAX;
+        let first = simulate(&config, &chunks, seed, 2, 10.0);
+        let second = simulate(&config, &chunks, seed, 2, 10.0);
+        assert_eq!(first, second);
+    }
+}
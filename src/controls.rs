@@ -0,0 +1,339 @@
+use crate::prelude::*;
+
+use geng::{Key, MouseButton};
+use gilrs::Button as PadButton;
+
+/// An action the player can perform, independent of the button it is bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveDown,
+    MoveUp,
+    Jump,
+    Shoot,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Self; 7] = [
+        Self::MoveLeft,
+        Self::MoveRight,
+        Self::MoveDown,
+        Self::MoveUp,
+        Self::Jump,
+        Self::Shoot,
+        Self::Pause,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::MoveLeft => "Move left",
+            Self::MoveRight => "Move right",
+            Self::MoveDown => "Move down",
+            Self::MoveUp => "Move up",
+            Self::Jump => "Jump",
+            Self::Shoot => "Shoot",
+            Self::Pause => "Pause",
+        }
+    }
+}
+
+/// A keyboard key, a mouse button or a gamepad button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Button {
+    Key(Key),
+    Mouse(MouseButton),
+    Gamepad(PadButton),
+}
+
+impl Button {
+    /// Returns the button pressed in the event, if any.
+    pub fn from_press(event: &geng::Event) -> Option<Self> {
+        match *event {
+            geng::Event::KeyPress { key } => Some(Self::Key(key)),
+            geng::Event::MousePress { button } => Some(Self::Mouse(button)),
+            _ => None,
+        }
+    }
+
+    /// Whether the key or the mouse button is held down.
+    /// Gamepad buttons are read by [`crate::gamepad::Gamepads`] instead.
+    pub fn is_pressed(self, window: &geng::Window) -> bool {
+        match self {
+            Self::Key(key) => window.is_key_pressed(key),
+            Self::Mouse(button) => window.is_button_pressed(button),
+            Self::Gamepad(_) => false,
+        }
+    }
+
+    pub fn is_gamepad(self) -> bool {
+        matches!(self, Self::Gamepad(_))
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            Self::Key(key) => format!("{key:?}"),
+            Self::Mouse(button) => format!("Mouse {button:?}"),
+            Self::Gamepad(button) => format!("Pad {button:?}"),
+        }
+    }
+}
+
+/// Buttons bound to each of the actions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Controls {
+    pub move_left: Vec<Button>,
+    pub move_right: Vec<Button>,
+    pub move_down: Vec<Button>,
+    pub move_up: Vec<Button>,
+    pub jump: Vec<Button>,
+    pub shoot: Vec<Button>,
+    #[serde(default = "default_pause")]
+    pub pause: Vec<Button>,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Self::qwerty()
+    }
+}
+
+impl Controls {
+    /// Layouts to choose from in the settings.
+    pub const PRESETS: [(&'static str, fn() -> Self); 3] = [
+        ("QWERTY", Self::qwerty),
+        ("AZERTY", Self::azerty),
+        ("Left-handed", Self::left_handed),
+    ];
+
+    pub fn qwerty() -> Self {
+        Self {
+            move_left: vec![Button::Key(Key::A), Button::Key(Key::ArrowLeft)],
+            move_right: vec![Button::Key(Key::D), Button::Key(Key::ArrowRight)],
+            move_down: vec![Button::Key(Key::S), Button::Key(Key::ArrowDown)],
+            move_up: vec![Button::Key(Key::W), Button::Key(Key::ArrowUp)],
+            jump: vec![Button::Key(Key::Space), Button::Gamepad(PadButton::South)],
+            shoot: vec![
+                Button::Mouse(MouseButton::Left),
+                Button::Gamepad(PadButton::West),
+                Button::Gamepad(PadButton::RightTrigger),
+                Button::Gamepad(PadButton::RightTrigger2),
+            ],
+            pause: default_pause(),
+        }
+    }
+
+    pub fn azerty() -> Self {
+        Self {
+            move_left: vec![Button::Key(Key::Q), Button::Key(Key::ArrowLeft)],
+            move_down: vec![Button::Key(Key::S), Button::Key(Key::ArrowDown)],
+            move_up: vec![Button::Key(Key::Z), Button::Key(Key::ArrowUp)],
+            ..Self::qwerty()
+        }
+    }
+
+    /// Movement on the right side of the keyboard, for the mouse in the left hand.
+    pub fn left_handed() -> Self {
+        Self {
+            move_left: vec![Button::Key(Key::J), Button::Key(Key::ArrowLeft)],
+            move_right: vec![Button::Key(Key::L), Button::Key(Key::ArrowRight)],
+            move_down: vec![Button::Key(Key::K), Button::Key(Key::ArrowDown)],
+            move_up: vec![Button::Key(Key::I), Button::Key(Key::ArrowUp)],
+            jump: vec![
+                Button::Key(Key::Enter),
+                Button::Key(Key::ShiftRight),
+                Button::Gamepad(PadButton::South),
+            ],
+            ..Self::qwerty()
+        }
+    }
+
+    /// Settings saved before gamepad buttons could be rebound have none bound,
+    /// so bind the default ones to the actions that have no gamepad button.
+    pub fn bind_missing_gamepad_buttons(&mut self) {
+        let defaults = Self::default();
+        for action in Action::ALL {
+            let buttons = self.get_mut(action);
+            if !buttons.iter().any(|button| button.is_gamepad()) {
+                let pad_buttons = defaults
+                    .get(action)
+                    .iter()
+                    .filter(|button| button.is_gamepad());
+                buttons.extend(pad_buttons);
+            }
+        }
+    }
+
+    /// Name of the preset these controls match, if any.
+    pub fn preset_name(&self) -> Option<&'static str> {
+        Self::PRESETS
+            .iter()
+            .find(|(_, preset)| preset() == *self)
+            .map(|(name, _)| *name)
+    }
+
+    pub fn get(&self, action: Action) -> &[Button] {
+        match action {
+            Action::MoveLeft => &self.move_left,
+            Action::MoveRight => &self.move_right,
+            Action::MoveDown => &self.move_down,
+            Action::MoveUp => &self.move_up,
+            Action::Jump => &self.jump,
+            Action::Shoot => &self.shoot,
+            Action::Pause => &self.pause,
+        }
+    }
+
+    pub fn get_mut(&mut self, action: Action) -> &mut Vec<Button> {
+        match action {
+            Action::MoveLeft => &mut self.move_left,
+            Action::MoveRight => &mut self.move_right,
+            Action::MoveDown => &mut self.move_down,
+            Action::MoveUp => &mut self.move_up,
+            Action::Jump => &mut self.jump,
+            Action::Shoot => &mut self.shoot,
+            Action::Pause => &mut self.pause,
+        }
+    }
+
+    /// Replace the primary button of the action, keeping the alternatives.
+    /// Gamepad buttons and the keyboard and mouse are bound separately.
+    /// The button is unbound from any other action, so it does only one thing.
+    pub fn rebind(&mut self, action: Action, button: Button) {
+        for other in Action::ALL {
+            self.get_mut(other).retain(|&other| other != button);
+        }
+        let buttons = self.get_mut(action);
+        match buttons
+            .iter_mut()
+            .find(|other| other.is_gamepad() == button.is_gamepad())
+        {
+            Some(primary) => *primary = button,
+            None => buttons.push(button),
+        }
+    }
+
+    pub fn is_pressed(&self, window: &geng::Window, action: Action) -> bool {
+        self.get(action)
+            .iter()
+            .any(|button| button.is_pressed(window))
+    }
+
+    pub fn is_event_press(&self, event: &geng::Event, action: Action) -> bool {
+        Button::from_press(event).map_or(false, |button| self.get(action).contains(&button))
+    }
+
+    /// The actions the gamepad button is bound to.
+    pub fn gamepad_actions(&self, button: PadButton) -> impl Iterator<Item = Action> + '_ {
+        Action::ALL
+            .into_iter()
+            .filter(move |&action| self.get(action).contains(&Button::Gamepad(button)))
+    }
+}
+
+fn default_pause() -> Vec<Button> {
+    vec![Button::Key(Key::Escape), Button::Gamepad(PadButton::Start)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every button is bound to at most one action.
+    fn assert_no_conflicts(controls: &Controls) {
+        for (i, &action) in Action::ALL.iter().enumerate() {
+            for &other in &Action::ALL[i + 1..] {
+                for button in controls.get(action) {
+                    assert!(
+                        !controls.get(other).contains(button),
+                        "{button:?} is bound to both {action:?} and {other:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn events_map_to_actions() {
+        let controls = Controls::default();
+        let space = geng::Event::KeyPress { key: Key::Space };
+        assert!(controls.is_event_press(&space, Action::Jump));
+        assert!(!controls.is_event_press(&space, Action::Shoot));
+
+        let click = geng::Event::MousePress {
+            button: MouseButton::Left,
+        };
+        assert!(controls.is_event_press(&click, Action::Shoot));
+        assert!(!controls.is_event_press(&click, Action::Jump));
+
+        let actions: Vec<Action> = controls.gamepad_actions(PadButton::South).collect();
+        assert_eq!(actions, [Action::Jump]);
+        assert_eq!(controls.gamepad_actions(PadButton::North).count(), 0);
+    }
+
+    #[test]
+    fn rebinding_replaces_the_primary_button() {
+        let mut controls = Controls::qwerty();
+        controls.rebind(Action::Jump, Button::Key(Key::J));
+        assert_eq!(
+            controls.jump,
+            [Button::Key(Key::J), Button::Gamepad(PadButton::South)]
+        );
+
+        // Gamepad buttons are bound separately from the keyboard
+        controls.rebind(Action::Jump, Button::Gamepad(PadButton::North));
+        assert_eq!(
+            controls.jump,
+            [Button::Key(Key::J), Button::Gamepad(PadButton::North)]
+        );
+
+        // Binding the alternative button makes it the only one
+        controls.rebind(Action::MoveLeft, Button::Key(Key::ArrowLeft));
+        assert_eq!(controls.move_left, [Button::Key(Key::ArrowLeft)]);
+    }
+
+    #[test]
+    fn rebinding_takes_the_button_from_other_actions() {
+        let mut controls = Controls::qwerty();
+        controls.rebind(Action::Jump, Button::Key(Key::W));
+        assert_eq!(controls.move_up, [Button::Key(Key::ArrowUp)]);
+        assert!(controls.jump.contains(&Button::Key(Key::W)));
+
+        controls.rebind(Action::Shoot, Button::Gamepad(PadButton::South));
+        assert!(!controls.jump.iter().any(|button| button.is_gamepad()));
+        let actions: Vec<Action> = controls.gamepad_actions(PadButton::South).collect();
+        assert_eq!(actions, [Action::Shoot]);
+        assert_no_conflicts(&controls);
+    }
+
+    #[test]
+    fn presets_are_recognized() {
+        for (name, preset) in Controls::PRESETS {
+            let controls = preset();
+            assert_eq!(controls.preset_name(), Some(name));
+            assert_no_conflicts(&controls);
+            for action in Action::ALL {
+                assert!(
+                    !controls.get(action).is_empty(),
+                    "{name}: {action:?} is unbound"
+                );
+            }
+        }
+        assert_eq!(Controls::default(), Controls::qwerty());
+
+        let mut controls = Controls::azerty();
+        assert_eq!(controls.move_up[0], Button::Key(Key::Z));
+        controls.rebind(Action::MoveUp, Button::Key(Key::W));
+        assert_eq!(controls.preset_name(), None);
+    }
+
+    #[test]
+    fn missing_gamepad_buttons_are_bound() {
+        let mut controls = Controls::qwerty();
+        controls.jump.retain(|button| !button.is_gamepad());
+        controls.shoot.retain(|button| !button.is_gamepad());
+        controls.bind_missing_gamepad_buttons();
+        assert_eq!(controls, Controls::qwerty());
+    }
+}
@@ -1,19 +1,42 @@
 use super::*;
 
+use highscores::{HighScore, HighScores};
+
 pub struct EndScreen {
     geng: Geng,
     assets: Rc<assets::Assets>,
     transition: Option<geng::state::Transition>,
-    score: i32,
+    entry: HighScore,
+    highscores: HighScores,
+    /// The place of this run in the high score table.
+    place: Option<usize>,
+    /// The number of players, to restart with the same amount.
+    players: usize,
+    /// Index of the player with the best score, if there were several players.
+    winner: Option<usize>,
 }
 
 impl EndScreen {
-    pub fn new(geng: &Geng, assets: &Rc<assets::Assets>, score: i32) -> Self {
+    /// `place` is the place of the `entry` in the `highscores`,
+    /// if it was recorded and got into the table.
+    pub fn new(
+        geng: &Geng,
+        assets: &Rc<assets::Assets>,
+        entry: HighScore,
+        highscores: HighScores,
+        place: Option<usize>,
+        players: usize,
+        winner: Option<usize>,
+    ) -> Self {
         Self {
             geng: geng.clone(),
             assets: assets.clone(),
             transition: None,
-            score,
+            entry,
+            highscores,
+            place,
+            players,
+            winner,
         }
     }
 }
@@ -33,22 +56,62 @@ impl geng::State for EndScreen {
                 vec2(self.assets.end.size().map(|x| x as f32).aspect(), 1.0) * camera.fov / 2.0,
             ),
         );
+        if let Some(winner) = self.winner {
+            self.geng.default_font().draw(
+                framebuffer,
+                &camera,
+                &format!("Player {} wins!", winner + 1),
+                vec2::splat(geng::TextAlign::CENTER),
+                mat3::translate(vec2(0.0, -1.0)) * mat3::scale_uniform(0.7),
+                "#90455a".try_into().unwrap(),
+            );
+        }
+        if self.place == Some(0) {
+            self.geng.default_font().draw(
+                framebuffer,
+                &camera,
+                "New record!",
+                vec2::splat(geng::TextAlign::CENTER),
+                mat3::translate(vec2(0.0, -2.0)) * mat3::scale_uniform(0.7),
+                "#e6ac4c".try_into().unwrap(),
+            );
+        }
         self.geng.default_font().draw(
             framebuffer,
             &camera,
-            &self.score.to_string(),
+            &self.entry.score.to_string(),
             vec2::splat(geng::TextAlign::CENTER),
             mat3::translate(vec2(0.0, -3.0)),
             "#90455a".try_into().unwrap(),
-        )
+        );
+        self.geng.default_font().draw(
+            framebuffer,
+            &camera,
+            &format!("seed: {}", self.entry.seed),
+            vec2::splat(geng::TextAlign::CENTER),
+            mat3::translate(vec2(0.0, -4.0)) * mat3::scale_uniform(0.5),
+            "#90455a".try_into().unwrap(),
+        );
+        self.highscores.draw(&self.geng, self.place, framebuffer);
     }
     fn handle_event(&mut self, event: geng::Event) {
         if let geng::Event::KeyPress { key: geng::Key::R } = event {
             self.transition = Some(geng::state::Transition::Switch(Box::new(game::Game::new(
                 &self.geng,
                 &self.assets,
+                None,
+                self.players,
+                None,
             ))));
         }
+        if let geng::Event::KeyPress {
+            key: geng::Key::Escape,
+        } = event
+        {
+            self.transition = Some(geng::state::Transition::Switch(Box::new(
+                menu::MainMenu::new(&self.geng, &self.assets),
+            )));
+        }
     }
     fn transition(&mut self) -> Option<geng::state::Transition> {
         self.transition.take()
@@ -1,84 +1,421 @@
-use crate::{prelude::*, render::GameRender};
+use crate::{
+    controls::Action,
+    gamepad::{GamepadInput, Gamepads},
+    highscores::{self, HighScore, HighScores},
+    menu::{draw_overlay, menu_camera, MainMenu, Menu, TEXT_COLOR},
+    net::{ClientMessage, OnlineRace, ResultsScreen},
+    prelude::*,
+    render::GameRender,
+    replay::{Recorder, Replay},
+    settings::Settings,
+};
 
-use geng::{Key, MouseButton};
+use geng::Key;
+use std::path::PathBuf;
+
+/// The rate at which the model is simulated.
+pub const TICKS_PER_SECOND: f64 = 120.0;
+/// Maximum time simulated in a single frame, so a lag spike does not freeze the game.
+pub const MAX_FRAME_TIME: f64 = 0.25;
+
+/// Where the world snapshot is saved to, next to the high scores.
+fn snapshot_path() -> PathBuf {
+    run_dir().join("snapshot.bin")
+}
 
 #[allow(dead_code)]
 pub struct Game {
     geng: Geng,
+    assets: Rc<Assets>,
     render: GameRender,
     model: Model,
+    transition: Option<geng::state::Transition>,
+    shhh: Option<geng::SoundEffect>,
+    settings: Settings,
+    gamepads: Gamepads,
+    /// While paused, the model is not updated and the pause menu is shown.
+    paused: bool,
+    pause_menu: Menu,
+    /// Time that has passed but has not been simulated yet.
+    accumulator: f64,
+    /// Input of every simulated tick so far.
+    recording: Recorder,
+    /// Input to play back instead of reading the player's input.
+    playback: Option<std::vec::IntoIter<Vec<PlayerInput>>>,
+    /// The race against other players over the network, if playing online.
+    online: Option<OnlineRace>,
     jump: bool,
     shoot: bool,
     cursor_pos: vec2<f64>,
 }
 
 impl Game {
-    pub fn new(geng: &Geng, assets: &Rc<Assets>) -> Self {
+    pub fn new(
+        geng: &Geng,
+        assets: &Rc<Assets>,
+        seed: Option<u64>,
+        players: usize,
+        record_path: Option<PathBuf>,
+    ) -> Self {
+        let seed = seed.unwrap_or_else(|| thread_rng().gen());
+        let model = Model::new(assets.config.clone(), assets.chunks.clone(), seed, players);
+        Self::from_model(geng, assets, model, record_path)
+    }
+
+    fn from_model(
+        geng: &Geng,
+        assets: &Rc<Assets>,
+        model: Model,
+        record_path: Option<PathBuf>,
+    ) -> Self {
         Self {
             geng: geng.clone(),
-            render: GameRender::new(geng, assets),
-            model: Model::new(geng.clone(), assets.clone()),
+            assets: assets.clone(),
+            render: GameRender::new(geng, assets, model.seed),
+            recording: Recorder::new(&model, record_path),
+            model,
+            transition: None,
+            shhh: None,
+            settings: Settings::load(),
+            gamepads: Gamepads::new(),
+            paused: false,
+            pause_menu: Menu::new(["Resume", "Restart", "Quit to menu"]),
+            accumulator: 0.0,
+            playback: None,
+            online: None,
             jump: false,
             shoot: false,
             cursor_pos: vec2::ZERO,
         }
     }
 
-    fn player_input(&mut self) -> PlayerInput {
+    /// Play back a recorded run, in the world it was recorded in.
+    pub fn replay(geng: &Geng, assets: &Rc<Assets>, replay: Replay) -> Self {
+        if !replay.matches(&assets.config, &assets.chunks) {
+            log::warn!(
+                "The assets have changed since the recording, playing back with the old ones"
+            );
+        }
+        let model = replay.model();
+        Self {
+            playback: Some(replay.inputs.into_iter()),
+            ..Self::from_model(geng, assets, model, None)
+        }
+    }
+
+    /// Race against other players over the network on the same seed.
+    pub fn online(geng: &Geng, assets: &Rc<Assets>, seed: u64, race: OnlineRace) -> Self {
+        Self {
+            online: Some(race),
+            ..Self::new(geng, assets, Some(seed), 1, None)
+        }
+    }
+
+    /// Resume a run from a saved snapshot.
+    pub fn resume(geng: &Geng, assets: &Rc<Assets>, snapshot: Snapshot) -> Self {
+        Self::from_model(geng, assets, Model::from_snapshot(snapshot), None)
+    }
+
+    /// Returns the input of every player for the next tick,
+    /// or `None` if the played back recording has ended.
+    fn next_inputs(&mut self) -> Option<Vec<PlayerInput>> {
+        let inputs = match &mut self.playback {
+            Some(inputs) => inputs.next()?,
+            None => self.player_inputs(),
+        };
+        self.recording.record(inputs.clone());
+        Some(inputs)
+    }
+
+    /// The first player uses the keyboard and the mouse,
+    /// the others use a gamepad each.
+    /// When playing alone, any gamepad controls the player too.
+    fn player_inputs(&mut self) -> Vec<PlayerInput> {
+        let players = self.model.players.len();
+        let first = if players == 1 {
+            self.gamepads.take_input_all()
+        } else {
+            GamepadInput::default()
+        };
+        let mut inputs = vec![self.keyboard_input(first)];
+        for i in 1..players {
+            let gamepad = self.gamepads.take_input(i - 1);
+            inputs.push(PlayerInput {
+                input_dir: gamepad.move_dir.as_r32(),
+                jump: gamepad.jump,
+                shoot: gamepad.shoot,
+                aim: Aim::Direction(gamepad.aim.unwrap_or(vec2::UNIT_Y).as_r32()),
+            });
+        }
+        inputs
+    }
+
+    /// Input from the keyboard and the mouse, combined with the `gamepad`.
+    fn keyboard_input(&mut self, gamepad: GamepadInput) -> PlayerInput {
         let mut move_dir = vec2::<f32>::ZERO;
         let window = self.geng.window();
-        if geng_utils::key::is_key_pressed(window, [Key::A, Key::ArrowLeft]) {
+        let controls = &self.settings.controls;
+        if controls.is_pressed(window, Action::MoveLeft) {
             move_dir.x -= 1.0;
         }
-        if geng_utils::key::is_key_pressed(window, [Key::D, Key::ArrowRight]) {
+        if controls.is_pressed(window, Action::MoveRight) {
             move_dir.x += 1.0;
         }
-        if geng_utils::key::is_key_pressed(window, [Key::S, Key::ArrowDown]) {
+        if controls.is_pressed(window, Action::MoveDown) {
             move_dir.y -= 1.0;
         }
-        if geng_utils::key::is_key_pressed(window, [Key::W, Key::ArrowUp]) {
+        if controls.is_pressed(window, Action::MoveUp) {
             move_dir.y += 1.0;
         }
-        let move_dir = move_dir.as_r32();
+
+        let move_dir = (move_dir + gamepad.move_dir)
+            .map(|x| x.clamp_abs(1.0))
+            .as_r32();
+        let aim = match gamepad.aim {
+            Some(dir) => Aim::Direction(dir.as_r32()),
+            None => Aim::Position(self.model.camera.cursor_pos_world()),
+        };
 
         PlayerInput {
             input_dir: move_dir,
-            jump: std::mem::take(&mut self.jump),
-            shoot: std::mem::take(&mut self.shoot),
+            jump: std::mem::take(&mut self.jump) || gamepad.jump,
+            shoot: std::mem::take(&mut self.shoot) || gamepad.shoot,
+            aim,
         }
     }
+
+    fn handle_events(&mut self) {
+        for event in self.model.take_events() {
+            self.event_sound(&event);
+            if let Event::Died { .. } = event {
+                if self.model.is_game_over() {
+                    self.game_over();
+                }
+            }
+        }
+
+        self.update_shhh();
+    }
+
+    fn update_shhh(&mut self) {
+        if self.model.shhh_playing && !self.paused {
+            let volume = self.model.shhh_volume * self.settings.sfx_volume;
+            let sfx = self.shhh.get_or_insert_with(|| {
+                let mut sfx = self.assets.sfx.shhh.effect();
+                sfx.set_volume(volume);
+                sfx.play();
+                sfx
+            });
+            sfx.set_volume(volume);
+        } else if let Some(mut sfx) = self.shhh.take() {
+            sfx.stop();
+        }
+    }
+
+    fn event_sound(&self, event: &Event) {
+        let sfx = &self.assets.sfx;
+        let (sound, volume) = match event {
+            Event::Jumped { .. } => (&sfx.jump, 1.0),
+            Event::SpringBounced { .. } => (&sfx.spring, 1.0),
+            Event::BirdHitPlayer { .. } => (&sfx.oi, 1.0),
+            Event::BirdWounded { .. } => (&sfx.kill_bird, 0.4),
+            Event::BirdKilled { .. } => (&sfx.kill_bird, 1.0),
+            Event::ShotFired { .. } => (&sfx.shoot, 1.0),
+            Event::CoinCollected { .. } => (&sfx.coin, 0.2),
+            Event::PowerUpCollected { .. } => (&sfx.coin, 0.5),
+            Event::ShieldBroken { .. } => (&sfx.kill_bird, 1.0),
+            Event::LandedOnCloud { .. } | Event::Died { .. } => return,
+        };
+        let mut sfx = sound.effect();
+        sfx.set_volume(volume * self.settings.sfx_volume);
+        sfx.play();
+    }
+
+    /// Exchange the race state with the other players.
+    fn update_online(&mut self) {
+        let Some(race) = &mut self.online else {
+            return;
+        };
+        race.update();
+        if self.paused {
+            // Nothing changes while paused
+            return;
+        }
+        let player = &self.model.players[0];
+        if let Some(id) = player.body {
+            let (position,) = get!(self.model.doodles, id, (&body.collider.position)).unwrap();
+            race.send(&ClientMessage::State {
+                position: position.to_world().as_f32(),
+                score: player.total_score(),
+            });
+        }
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.pause_menu.selected = 0;
+        self.update_shhh();
+    }
+
+    fn handle_pause_event(&mut self, event: &geng::Event) {
+        use geng::state::Transition;
+        match self.pause_menu.handle_event(event) {
+            Some(0) => self.paused = false,
+            Some(1) => {
+                self.transition = Some(Transition::Switch(Box::new(Self::new(
+                    &self.geng,
+                    &self.assets,
+                    None,
+                    self.model.players.len(),
+                    None,
+                ))));
+            }
+            Some(2) => {
+                self.transition = Some(Transition::Switch(Box::new(MainMenu::new(
+                    &self.geng,
+                    &self.assets,
+                ))));
+            }
+            _ => {}
+        }
+    }
+
+    fn draw_pause(&self, framebuffer: &mut ugli::Framebuffer) {
+        let camera = menu_camera();
+        draw_overlay(&self.geng, 0.8, framebuffer);
+        self.geng.default_font().draw(
+            framebuffer,
+            &camera,
+            "Paused",
+            vec2::splat(geng::TextAlign::CENTER),
+            mat3::translate(vec2(0.0, 2.0)) * mat3::scale_uniform(1.2),
+            TEXT_COLOR.try_into().unwrap(),
+        );
+        self.pause_menu
+            .draw(&self.geng, &camera, vec2(0.0, 0.0), framebuffer);
+    }
+
+    fn game_over(&mut self) {
+        if self.transition.is_some() {
+            return;
+        }
+        self.recording.save();
+        if let Some(race) = self.online.take() {
+            let score = self.model.players[0].total_score();
+            self.transition = Some(geng::state::Transition::Switch(Box::new(
+                ResultsScreen::new(&self.geng, &self.assets, race, score),
+            )));
+            return;
+        }
+
+        let players = &self.model.players;
+        let (winner, best) = players
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, player)| player.total_score())
+            .unwrap();
+        let entry = HighScore {
+            score: best.total_score(),
+            date: chrono::Local::now().date_naive(),
+            seed: self.model.seed,
+            max_height: best.stats.max_height,
+            coins_collected: best.stats.coins_collected,
+        };
+        let winner = (players.len() > 1).then_some(winner);
+
+        let mut highscores = HighScores::load(highscores::default_storage());
+        let place = highscores.submit(entry.clone(), self.playback.is_some());
+        self.transition = Some(geng::state::Transition::Switch(Box::new(
+            crate::end_screen::EndScreen::new(
+                &self.geng,
+                &self.assets,
+                entry,
+                highscores,
+                place,
+                players.len(),
+                winner,
+            ),
+        )));
+    }
 }
 
 impl geng::State for Game {
     fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
         ugli::clear(framebuffer, Some(Rgba::BLACK), None, None);
         self.model.camera.framebuffer_size = framebuffer.size();
-        self.render.draw(&self.model, framebuffer);
+        let alpha = (self.accumulator * TICKS_PER_SECOND) as f32;
+        self.render.draw(&self.model, alpha, framebuffer);
+        if let Some(race) = &self.online {
+            let ghosts: Vec<_> = race
+                .ghosts
+                .values()
+                .filter(|ghost| !ghost.finished)
+                .collect();
+            self.render
+                .draw_ghosts(&self.model, alpha, &ghosts, framebuffer);
+        }
+        if self.paused {
+            self.draw_pause(framebuffer);
+        }
     }
 
     fn handle_event(&mut self, event: geng::Event) {
-        if geng_utils::key::is_event_press(&event, [Key::Space]) {
+        if self.settings.controls.is_event_press(&event, Action::Pause) {
+            self.toggle_pause();
+            return;
+        }
+        if self.paused {
+            self.handle_pause_event(&event);
+            return;
+        }
+
+        if self.settings.controls.is_event_press(&event, Action::Jump) {
             self.jump = true;
         }
-        if geng_utils::key::is_event_press(&event, [MouseButton::Left]) {
+        if self.settings.controls.is_event_press(&event, Action::Shoot) {
             self.shoot = true;
         }
 
+        if geng_utils::key::is_event_press(&event, [Key::F5]) {
+            let path = snapshot_path();
+            match self.model.to_snapshot().save(&path) {
+                Ok(()) => log::info!("Saved the snapshot to {path:?}"),
+                Err(err) => log::error!("{err:?}"),
+            }
+        }
+
         if let geng::Event::CursorMove { position } = event {
             self.cursor_pos = position;
+            self.gamepads.reset_aim();
         }
     }
 
     fn transition(&mut self) -> Option<geng::state::Transition> {
-        self.model.transition.take()
+        self.transition.take()
     }
 
     fn update(&mut self, delta_time: f64) {
-        let delta_time = Time::new(delta_time as _);
-
+        self.gamepads.poll(&self.settings.controls);
+        if self.gamepads.take_pause() {
+            self.toggle_pause();
+        }
+        self.update_online();
+        if self.paused {
+            return;
+        }
         self.model.camera.cursor_pos = self.cursor_pos;
 
-        let input = self.player_input();
-        self.model.update(input, delta_time);
+        let fixed_delta_time = TICKS_PER_SECOND.recip();
+        self.accumulator = (self.accumulator + delta_time).min(MAX_FRAME_TIME);
+        while self.accumulator >= fixed_delta_time {
+            self.accumulator -= fixed_delta_time;
+            let Some(inputs) = self.next_inputs() else {
+                // The recording has ended, so the run is over
+                self.game_over();
+                break;
+            };
+            self.model.update(&inputs, Time::new(fixed_delta_time as _));
+            self.handle_events();
+        }
     }
 }
@@ -0,0 +1,196 @@
+use crate::{
+    controls::{Action, Controls},
+    prelude::*,
+};
+
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+
+/// Stick deflection below which the input is ignored.
+const DEADZONE: f32 = 0.2;
+
+/// Input read from the gamepads since the last tick.
+#[derive(Debug, Clone, Copy)]
+pub struct GamepadInput {
+    pub move_dir: vec2<f32>,
+    /// Direction of the right stick, if it was the last thing used to aim.
+    pub aim: Option<vec2<f32>>,
+    pub jump: bool,
+    pub shoot: bool,
+}
+
+impl Default for GamepadInput {
+    fn default() -> Self {
+        Self {
+            move_dir: vec2::ZERO,
+            aim: None,
+            jump: false,
+            shoot: false,
+        }
+    }
+}
+
+/// Button presses of a single gamepad, not yet taken.
+struct PadState {
+    id: GamepadId,
+    jump: bool,
+    shoot: bool,
+    aim: Option<vec2<f32>>,
+}
+
+impl PadState {
+    fn new(id: GamepadId) -> Self {
+        Self {
+            id,
+            jump: false,
+            shoot: false,
+            aim: None,
+        }
+    }
+}
+
+/// Reads the input of the connected gamepads,
+/// either separately for each player or combined.
+pub struct Gamepads {
+    /// `None` if the gamepad backend is not available on this platform.
+    gilrs: Option<Gilrs>,
+    /// Gamepads in the order they were connected.
+    pads: Vec<PadState>,
+    pause: bool,
+    /// The last button pressed on any gamepad, for rebinding.
+    last_pressed: Option<Button>,
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        let gilrs = Gilrs::new()
+            .map_err(|err| log::error!("Gamepads are not available: {err}"))
+            .ok();
+        let pads = gilrs
+            .iter()
+            .flat_map(|gilrs| gilrs.gamepads())
+            .map(|(id, _)| PadState::new(id))
+            .collect();
+        Self {
+            gilrs,
+            pads,
+            pause: false,
+            last_pressed: None,
+        }
+    }
+
+    /// The number of connected gamepads.
+    pub fn count(&self) -> usize {
+        let Some(gilrs) = &self.gilrs else {
+            return 0;
+        };
+        self.pads
+            .iter()
+            .filter(|pad| gilrs.gamepad(pad.id).is_connected())
+            .count()
+    }
+
+    /// The number of gamepads needed for the local players,
+    /// since the first player uses the keyboard and the mouse.
+    pub fn needed(players: usize) -> usize {
+        players.saturating_sub(1)
+    }
+
+    /// Process the pending gamepad events, mapping the buttons to actions
+    /// through the `controls`. Should be called every frame.
+    pub fn poll(&mut self, controls: &Controls) {
+        let Some(gilrs) = &mut self.gilrs else {
+            return;
+        };
+        while let Some(event) = gilrs.next_event() {
+            let pad = match self.pads.iter().position(|pad| pad.id == event.id) {
+                Some(i) => &mut self.pads[i],
+                None => {
+                    self.pads.push(PadState::new(event.id));
+                    self.pads.last_mut().unwrap()
+                }
+            };
+            if let EventType::ButtonPressed(button, _) = event.event {
+                self.last_pressed = Some(button);
+                for action in controls.gamepad_actions(button) {
+                    match action {
+                        Action::Jump => pad.jump = true,
+                        Action::Shoot => pad.shoot = true,
+                        Action::Pause => self.pause = true,
+                        // Movement is read from the sticks
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        for pad in &mut self.pads {
+            let aim = stick(gilrs, pad.id, Axis::RightStickX, Axis::RightStickY);
+            if aim != vec2::ZERO {
+                pad.aim = Some(aim);
+            }
+        }
+    }
+
+    /// The last button pressed on any gamepad since the last call.
+    pub fn take_pressed(&mut self) -> Option<Button> {
+        self.last_pressed.take()
+    }
+
+    /// Whether the pause button was pressed since the last call.
+    pub fn take_pause(&mut self) -> bool {
+        std::mem::take(&mut self.pause)
+    }
+
+    /// Stop aiming with the sticks, e.g. because the mouse was moved.
+    pub fn reset_aim(&mut self) {
+        for pad in &mut self.pads {
+            pad.aim = None;
+        }
+    }
+
+    /// Returns the current input of the gamepad at `index` and resets its button presses.
+    pub fn take_input(&mut self, index: usize) -> GamepadInput {
+        let Some(pad) = self.pads.get_mut(index) else {
+            return GamepadInput::default();
+        };
+        GamepadInput {
+            move_dir: match &self.gilrs {
+                Some(gilrs) => stick(gilrs, pad.id, Axis::LeftStickX, Axis::LeftStickY),
+                None => vec2::ZERO,
+            },
+            aim: pad.aim,
+            jump: std::mem::take(&mut pad.jump),
+            shoot: std::mem::take(&mut pad.shoot),
+        }
+    }
+
+    /// Returns the input of all gamepads combined and resets the button presses.
+    pub fn take_input_all(&mut self) -> GamepadInput {
+        (0..self.pads.len()).map(|i| self.take_input(i)).fold(
+            GamepadInput::default(),
+            |acc, input| GamepadInput {
+                move_dir: (acc.move_dir + input.move_dir).clamp_len(..=1.0),
+                aim: input.aim.or(acc.aim),
+                jump: acc.jump || input.jump,
+                shoot: acc.shoot || input.shoot,
+            },
+        )
+    }
+}
+
+/// Position of the stick of the gamepad, ignoring the deadzone.
+fn stick(gilrs: &Gilrs, id: GamepadId, x: Axis, y: Axis) -> vec2<f32> {
+    let gamepad = gilrs.gamepad(id);
+    let stick = vec2(gamepad.value(x), gamepad.value(y));
+    if gamepad.is_connected() && stick.len() > DEADZONE {
+        stick
+    } else {
+        vec2::ZERO
+    }
+}
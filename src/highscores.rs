@@ -0,0 +1,205 @@
+use crate::prelude::*;
+
+/// The number of entries kept in the table.
+const MAX_ENTRIES: usize = 10;
+/// The key the high scores are stored under.
+const HIGHSCORES_KEY: &str = "highscores";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighScore {
+    pub score: i32,
+    pub date: chrono::NaiveDate,
+    pub seed: u64,
+    pub max_height: Coord,
+    pub coins_collected: u32,
+}
+
+/// Where the high scores are persisted.
+pub trait ScoreStorage {
+    fn load(&self) -> anyhow::Result<Vec<HighScore>>;
+    fn save(&self, scores: &[HighScore]) -> anyhow::Result<()>;
+}
+
+/// Stores the high scores in the preferences, together with the settings.
+pub struct PreferencesStorage;
+
+impl ScoreStorage for PreferencesStorage {
+    fn load(&self) -> anyhow::Result<Vec<HighScore>> {
+        Ok(preferences::load(HIGHSCORES_KEY).unwrap_or_default())
+    }
+
+    fn save(&self, scores: &[HighScore]) -> anyhow::Result<()> {
+        preferences::save(HIGHSCORES_KEY, &scores);
+        Ok(())
+    }
+}
+
+/// The storage used by the game.
+pub fn default_storage() -> Box<dyn ScoreStorage> {
+    Box::new(PreferencesStorage)
+}
+
+/// The table of the best runs, sorted from best to worst.
+pub struct HighScores {
+    storage: Box<dyn ScoreStorage>,
+    pub scores: Vec<HighScore>,
+}
+
+impl HighScores {
+    pub fn load(storage: Box<dyn ScoreStorage>) -> Self {
+        let scores = storage.load().unwrap_or_else(|err| {
+            log::error!("{err:?}");
+            Vec::new()
+        });
+        Self { storage, scores }
+    }
+
+    /// Insert a new entry into the table, and save it.
+    /// Returns the place of the entry in the table, if it got into it.
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        let place = self
+            .scores
+            .iter()
+            .position(|other| entry.score > other.score)
+            .unwrap_or(self.scores.len());
+        if place >= MAX_ENTRIES {
+            return None;
+        }
+
+        self.scores.insert(place, entry);
+        self.scores.truncate(MAX_ENTRIES);
+        if let Err(err) = self.storage.save(&self.scores) {
+            log::error!("{err:?}");
+        }
+        Some(place)
+    }
+
+    /// Submit the entry of a finished run.
+    /// Played back runs have already been recorded when they were played,
+    /// so they are not inserted again.
+    pub fn submit(&mut self, entry: HighScore, played_back: bool) -> Option<usize> {
+        if played_back {
+            return None;
+        }
+        self.insert(entry)
+    }
+
+    /// Draw the table in the top left corner of the screen,
+    /// with the entry at `highlight` place highlighted.
+    pub fn draw(&self, geng: &Geng, highlight: Option<usize>, framebuffer: &mut ugli::Framebuffer) {
+        let font_size = framebuffer.size().y as f32 * 0.03;
+        let origin = vec2(0.02, 0.98) * framebuffer.size().as_f32();
+        for (i, entry) in self.scores.iter().enumerate() {
+            let color = if Some(i) == highlight {
+                crate::menu::SELECTED_COLOR
+            } else {
+                crate::menu::TEXT_COLOR
+            };
+            geng.default_font().draw(
+                framebuffer,
+                &geng::PixelPerfectCamera,
+                &format!(
+                    "{}. {}  height {:.0}  coins {}  {}  seed {}",
+                    i + 1,
+                    entry.score,
+                    entry.max_height.as_f32(),
+                    entry.coins_collected,
+                    entry.date,
+                    entry.seed
+                ),
+                vec2(geng::TextAlign::LEFT, geng::TextAlign::TOP),
+                mat3::translate(origin - vec2(0.0, font_size * 1.2 * i as f32))
+                    * mat3::scale_uniform(font_size)
+                    * mat3::translate(vec2(0.0, -0.5)),
+                color.try_into().unwrap(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{cell::RefCell, rc::Rc};
+
+    /// Keeps the saved scores in memory, shared with the test.
+    #[derive(Default, Clone)]
+    struct MemoryStorage {
+        saved: Rc<RefCell<Vec<HighScore>>>,
+    }
+
+    impl ScoreStorage for MemoryStorage {
+        fn load(&self) -> anyhow::Result<Vec<HighScore>> {
+            Ok(self.saved.borrow().clone())
+        }
+
+        fn save(&self, scores: &[HighScore]) -> anyhow::Result<()> {
+            *self.saved.borrow_mut() = scores.to_vec();
+            Ok(())
+        }
+    }
+
+    fn entry(score: i32) -> HighScore {
+        HighScore {
+            score,
+            date: chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            seed: score as u64,
+            max_height: r32(score as f32),
+            coins_collected: 0,
+        }
+    }
+
+    fn scores(table: &[HighScore]) -> Vec<i32> {
+        table.iter().map(|entry| entry.score).collect()
+    }
+
+    #[test]
+    fn entries_are_inserted_sorted() {
+        let storage = MemoryStorage::default();
+        let mut highscores = HighScores::load(Box::new(storage.clone()));
+        assert_eq!(highscores.insert(entry(10)), Some(0));
+        assert_eq!(highscores.insert(entry(30)), Some(0));
+        assert_eq!(highscores.insert(entry(20)), Some(1));
+        // Ties go after the older entries
+        assert_eq!(highscores.insert(entry(20)), Some(2));
+        assert_eq!(scores(&highscores.scores), [30, 20, 20, 10]);
+        assert_eq!(scores(&storage.saved.borrow()), [30, 20, 20, 10]);
+
+        let reloaded = HighScores::load(Box::new(storage));
+        assert_eq!(scores(&reloaded.scores), [30, 20, 20, 10]);
+    }
+
+    #[test]
+    fn table_is_cut_at_the_limit() {
+        let storage = MemoryStorage::default();
+        let mut highscores = HighScores::load(Box::new(storage.clone()));
+        for score in 1..=MAX_ENTRIES as i32 {
+            highscores.insert(entry(score * 10));
+        }
+        assert_eq!(highscores.scores.len(), MAX_ENTRIES);
+
+        // Too low to get into the full table
+        assert_eq!(highscores.insert(entry(5)), None);
+        assert_eq!(highscores.scores.len(), MAX_ENTRIES);
+        assert_eq!(highscores.scores.last().unwrap().score, 10);
+
+        // The worst entry is pushed out
+        assert_eq!(highscores.insert(entry(15)), Some(MAX_ENTRIES - 1));
+        assert_eq!(highscores.scores.len(), MAX_ENTRIES);
+        assert_eq!(highscores.scores.last().unwrap().score, 15);
+        assert_eq!(storage.saved.borrow().len(), MAX_ENTRIES);
+    }
+
+    #[test]
+    fn played_back_runs_are_skipped() {
+        let storage = MemoryStorage::default();
+        let mut highscores = HighScores::load(Box::new(storage.clone()));
+        assert_eq!(highscores.submit(entry(10), true), None);
+        assert!(highscores.scores.is_empty());
+        assert!(storage.saved.borrow().is_empty());
+
+        assert_eq!(highscores.submit(entry(10), false), Some(0));
+        assert_eq!(scores(&storage.saved.borrow()), [10]);
+    }
+}
@@ -1,14 +1,48 @@
 mod assets;
+mod bot;
+mod controls;
+mod end_screen;
 mod game;
+mod gamepad;
+mod highscores;
+mod menu;
 mod model;
+mod net;
 mod prelude;
 mod render;
-mod end_screen;
+mod replay;
+mod settings;
 
 use geng::prelude::*;
 
 #[derive(clap::Parser)]
 struct Opts {
+    /// Seed for the level generation. Random if not specified.
+    #[clap(long)]
+    seed: Option<u64>,
+    /// Number of local players, from 1 to 4.
+    #[clap(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=4))]
+    players: u8,
+    /// Save the input of the run to the given file once it is over.
+    #[clap(long)]
+    record: Option<std::path::PathBuf>,
+    /// Play back a run recorded with `--record`.
+    #[clap(long)]
+    replay: Option<std::path::PathBuf>,
+    /// Resume a run from a snapshot saved with F5 to `snapshot.bin` in the game's directory.
+    #[clap(long)]
+    load: Option<std::path::PathBuf>,
+    /// Race online against other players, connecting to the server at the address.
+    /// Run the server with `cargo run --bin server`.
+    #[clap(long, num_args = 0..=1, default_missing_value = net::DEFAULT_ADDR)]
+    connect: Option<String>,
+    /// Play the given number of games with the bot without opening a window,
+    /// and print the statistics. Uses `--seed` as the seed of the first game.
+    #[clap(long)]
+    simulate: Option<usize>,
+    /// Game time limit for each simulated game, in seconds.
+    #[clap(long, default_value_t = 300.0)]
+    simulate_time: f64,
     #[clap(flatten)]
     geng: geng::CliArgs,
 }
@@ -19,17 +53,105 @@ fn main() {
 
     let opts: Opts = clap::Parser::parse();
 
+    if let Some(games) = opts.simulate {
+        if let Err(err) = simulate(games, opts.seed.unwrap_or(0), opts.simulate_time) {
+            log::error!("{err:?}");
+        }
+        return;
+    }
+
     let mut geng_opts = geng::ContextOptions::default();
     geng_opts.window.title = "Doodle Shoot".to_string();
     geng_opts.with_cli(&opts.geng);
 
-    Geng::run_with(&geng_opts, |geng| async move {
+    let replay = match opts.replay.as_ref().map(replay::Replay::load).transpose() {
+        Ok(replay) => replay,
+        Err(err) => {
+            log::error!("{err:?}");
+            std::process::exit(1);
+        }
+    };
+    let snapshot = match opts.load.as_ref().map(model::Snapshot::load).transpose() {
+        Ok(snapshot) => snapshot,
+        Err(err) => {
+            log::error!("{err:?}");
+            std::process::exit(1);
+        }
+    };
+    let seed = opts.seed;
+    let record = opts.record;
+    let players = opts.players as usize;
+    let connect = opts.connect;
+
+    // Every local player but the first needs a gamepad
+    if replay.is_none() && connect.is_none() {
+        let players = snapshot
+            .as_ref()
+            .map_or(players, |snapshot| snapshot.players.len());
+        let needed = gamepad::Gamepads::needed(players);
+        let connected = gamepad::Gamepads::new().count();
+        if connected < needed {
+            log::error!("{players} players need {needed} gamepads, but {connected} are connected");
+            std::process::exit(1);
+        }
+    }
+    Geng::run_with(&geng_opts, move |geng| async move {
         let manager = geng.asset_manager();
         let assets = assets::Assets::load(manager).await.unwrap();
         let mut music = assets.music.effect();
         music.set_volume(0.5);
         music.play();
-        let game = game::Game::new(&geng, &Rc::new(assets));
-        geng.run_state(game).await;
+        let assets = Rc::new(assets);
+        match (replay, snapshot, connect) {
+            (Some(replay), _, _) => {
+                geng.run_state(game::Game::replay(&geng, &assets, replay))
+                    .await
+            }
+            (None, Some(snapshot), _) => {
+                geng.run_state(game::Game::resume(&geng, &assets, snapshot))
+                    .await
+            }
+            (None, None, Some(addr)) => {
+                geng.run_state(net::Lobby::new(&geng, &assets, &addr)).await
+            }
+            (None, None, None) if seed.is_some() || record.is_some() || players > 1 => {
+                geng.run_state(game::Game::new(&geng, &assets, seed, players, record))
+                    .await
+            }
+            (None, None, None) => geng.run_state(menu::MainMenu::new(&geng, &assets)).await,
+        }
     });
 }
+
+/// Play games with the bot headless, to see how the config affects the score.
+fn simulate(games: usize, seed: u64, max_time: f64) -> anyhow::Result<()> {
+    let assets_path = run_dir().join("assets");
+    let read_ron = |name: &str| -> anyhow::Result<String> {
+        let path = assets_path.join(name);
+        std::fs::read_to_string(&path).with_context(|| format!("failed to read {path:?}"))
+    };
+    let config: model::Config = ron::from_str(&read_ron("config.ron")?)?;
+    config.validate().context("invalid config.ron")?;
+    let chunks: Vec<model::Chunk> = ron::from_str(&read_ron("chunks.ron")?)?;
+    model::validate_chunks(&chunks).context("invalid chunks.ron")?;
+
+    anyhow::ensure!(games > 0, "need at least one game to simulate");
+    let stats = bot::simulate(&config, &chunks, seed, games, max_time);
+    let mut scores = stats.scores;
+    scores.sort();
+    let mean = |values: &[f32]| values.iter().sum::<f32>() / values.len() as f32;
+    println!("Simulated {games} games starting from seed {seed}");
+    println!(
+        "Score: mean {:.1}, median {}, min {}, max {}",
+        mean(&scores.iter().map(|&x| x as f32).collect::<Vec<_>>()),
+        scores[scores.len() / 2],
+        scores[0],
+        scores[scores.len() - 1],
+    );
+    println!(
+        "Height: mean {:.1}, max {:.1}",
+        mean(&stats.heights),
+        stats.heights.iter().copied().fold(0.0, f32::max),
+    );
+    Ok(())
+}
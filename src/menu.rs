@@ -0,0 +1,278 @@
+use crate::{
+    bot::Bot, controls::Controls, gamepad::Gamepads, highscores::HighScores, prelude::*,
+    render::GameRender, settings::SettingsScreen,
+};
+
+use geng::Key;
+
+pub const TEXT_COLOR: &str = "#90455a";
+pub const SELECTED_COLOR: &str = "#e6ac4c";
+pub const BACKGROUND_COLOR: &str = "#e3e9f8";
+
+/// The camera used to draw menus.
+pub fn menu_camera() -> geng::Camera2d {
+    geng::Camera2d {
+        center: vec2::ZERO,
+        rotation: Angle::ZERO,
+        fov: 10.0,
+    }
+}
+
+/// Cover the whole screen with the background color, keeping what is under it visible.
+pub fn draw_overlay(geng: &Geng, alpha: f32, framebuffer: &mut ugli::Framebuffer) {
+    let mut color: Color = BACKGROUND_COLOR.try_into().unwrap();
+    color.a = alpha;
+    let screen = Aabb2::ZERO.extend_positive(framebuffer.size().as_f32());
+    geng.draw2d().draw2d(
+        framebuffer,
+        &geng::PixelPerfectCamera,
+        &draw2d::Quad::new(screen, color),
+    );
+}
+
+/// A vertical list of options navigated with the keyboard.
+pub struct Menu {
+    pub options: Vec<String>,
+    pub selected: usize,
+}
+
+impl Menu {
+    pub fn new(options: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            options: options.into_iter().map(Into::into).collect(),
+            selected: 0,
+        }
+    }
+
+    /// Returns the index of the option, if it was activated by the event.
+    pub fn handle_event(&mut self, event: &geng::Event) -> Option<usize> {
+        let len = self.options.len();
+        if geng_utils::key::is_event_press(event, [Key::W, Key::ArrowUp]) {
+            self.selected = (self.selected + len - 1) % len;
+        }
+        if geng_utils::key::is_event_press(event, [Key::S, Key::ArrowDown]) {
+            self.selected = (self.selected + 1) % len;
+        }
+        if geng_utils::key::is_event_press(event, [Key::Enter, Key::Space]) {
+            return Some(self.selected);
+        }
+        None
+    }
+
+    /// Draw the options one below the other, starting at `position`.
+    pub fn draw(
+        &self,
+        geng: &Geng,
+        camera: &geng::Camera2d,
+        position: vec2<f32>,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        for (i, option) in self.options.iter().enumerate() {
+            let (text, color) = if i == self.selected {
+                (format!("> {option} <"), SELECTED_COLOR)
+            } else {
+                (option.clone(), TEXT_COLOR)
+            };
+            geng.default_font().draw(
+                framebuffer,
+                camera,
+                &text,
+                vec2::splat(geng::TextAlign::CENTER),
+                mat3::translate(position - vec2(0.0, 1.2 * i as f32)) * mat3::scale_uniform(0.8),
+                color.try_into().unwrap(),
+            );
+        }
+    }
+}
+
+pub struct MainMenu {
+    geng: Geng,
+    assets: Rc<Assets>,
+    transition: Option<geng::state::Transition>,
+    menu: Menu,
+    /// The number of players in the local versus mode.
+    versus_players: usize,
+    /// To check that every versus player has a gamepad.
+    gamepads: Gamepads,
+    demo: Demo,
+}
+
+impl MainMenu {
+    pub fn new(geng: &Geng, assets: &Rc<Assets>) -> Self {
+        let mut menu = Self {
+            geng: geng.clone(),
+            assets: assets.clone(),
+            transition: None,
+            menu: Menu::new(["Play", "", "Settings", "High Scores", "Quit"]),
+            versus_players: 2,
+            gamepads: Gamepads::new(),
+            demo: Demo::new(geng, assets),
+        };
+        menu.update_options();
+        menu
+    }
+
+    fn update_options(&mut self) {
+        self.menu.options[1] = if self.missing_gamepads() > 0 {
+            format!(
+                "Versus: {} players (needs {} gamepads)",
+                self.versus_players,
+                Gamepads::needed(self.versus_players)
+            )
+        } else {
+            format!("Versus: {} players", self.versus_players)
+        };
+    }
+
+    /// The number of gamepads to connect before the versus mode can start.
+    fn missing_gamepads(&self) -> usize {
+        Gamepads::needed(self.versus_players).saturating_sub(self.gamepads.count())
+    }
+
+    fn start(&self, players: usize) -> geng::state::Transition {
+        geng::state::Transition::Switch(Box::new(crate::game::Game::new(
+            &self.geng,
+            &self.assets,
+            None,
+            players,
+            None,
+        )))
+    }
+}
+
+impl geng::State for MainMenu {
+    fn update(&mut self, delta_time: f64) {
+        // Only to notice gamepads being connected, the buttons do nothing here
+        self.gamepads.poll(&Controls::default());
+        self.update_options();
+        self.demo.update(delta_time);
+    }
+
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        let camera = menu_camera();
+        self.demo.draw(framebuffer);
+        draw_overlay(&self.geng, 0.6, framebuffer);
+        self.geng.default_font().draw(
+            framebuffer,
+            &camera,
+            "Doodle Shoot",
+            vec2::splat(geng::TextAlign::CENTER),
+            mat3::translate(vec2(0.0, 2.5)) * mat3::scale_uniform(1.5),
+            TEXT_COLOR.try_into().unwrap(),
+        );
+        self.menu
+            .draw(&self.geng, &camera, vec2(0.0, 0.5), framebuffer);
+    }
+
+    fn handle_event(&mut self, event: geng::Event) {
+        use geng::state::Transition;
+        if self.menu.selected == 1 {
+            if geng_utils::key::is_event_press(&event, [Key::A, Key::ArrowLeft]) {
+                self.versus_players = (self.versus_players - 1).max(2);
+            }
+            if geng_utils::key::is_event_press(&event, [Key::D, Key::ArrowRight]) {
+                self.versus_players = (self.versus_players + 1).min(4);
+            }
+            self.update_options();
+        }
+
+        self.transition = match self.menu.handle_event(&event) {
+            Some(0) => Some(self.start(1)),
+            Some(1) if self.missing_gamepads() > 0 => return,
+            Some(1) => Some(self.start(self.versus_players)),
+            Some(2) => Some(Transition::Push(Box::new(SettingsScreen::new(&self.geng)))),
+            Some(3) => Some(Transition::Push(Box::new(HighScoresScreen::new(
+                &self.geng,
+            )))),
+            Some(4) => Some(Transition::Pop),
+            _ => return,
+        };
+    }
+
+    fn transition(&mut self) -> Option<geng::state::Transition> {
+        self.transition.take()
+    }
+}
+
+pub struct HighScoresScreen {
+    geng: Geng,
+    transition: Option<geng::state::Transition>,
+    highscores: HighScores,
+}
+
+impl HighScoresScreen {
+    pub fn new(geng: &Geng) -> Self {
+        Self {
+            geng: geng.clone(),
+            transition: None,
+            highscores: HighScores::load(crate::highscores::default_storage()),
+        }
+    }
+}
+
+impl geng::State for HighScoresScreen {
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        ugli::clear(
+            framebuffer,
+            Some(BACKGROUND_COLOR.try_into().unwrap()),
+            None,
+            None,
+        );
+        self.highscores.draw(&self.geng, None, framebuffer);
+    }
+
+    fn handle_event(&mut self, event: geng::Event) {
+        if geng_utils::key::is_event_press(&event, [Key::Escape, Key::Enter, Key::Space]) {
+            self.transition = Some(geng::state::Transition::Pop);
+        }
+    }
+
+    fn transition(&mut self) -> Option<geng::state::Transition> {
+        self.transition.take()
+    }
+}
+
+/// The bot playing in the background of the main menu.
+struct Demo {
+    geng: Geng,
+    assets: Rc<Assets>,
+    render: GameRender,
+    model: Model,
+    bot: Bot,
+    accumulator: f64,
+}
+
+impl Demo {
+    fn new(geng: &Geng, assets: &Rc<Assets>) -> Self {
+        let seed = thread_rng().gen();
+        Self {
+            geng: geng.clone(),
+            assets: assets.clone(),
+            render: GameRender::new(geng, assets, seed),
+            model: Model::new(assets.config.clone(), assets.chunks.clone(), seed, 1),
+            bot: Bot::new(),
+            accumulator: 0.0,
+        }
+    }
+
+    fn update(&mut self, delta_time: f64) {
+        let fixed_delta_time = crate::game::TICKS_PER_SECOND.recip();
+        self.accumulator = (self.accumulator + delta_time).min(crate::game::MAX_FRAME_TIME);
+        while self.accumulator >= fixed_delta_time {
+            self.accumulator -= fixed_delta_time;
+            let input = self.bot.input(&self.model, 0);
+            self.model
+                .update(&[input], Time::new(fixed_delta_time as _));
+            if self.model.is_game_over() {
+                *self = Self::new(&self.geng, &self.assets);
+                return;
+            }
+        }
+    }
+
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        self.model.camera.framebuffer_size = framebuffer.size();
+        let alpha = (self.accumulator * crate::game::TICKS_PER_SECOND) as f32;
+        self.render.draw(&self.model, alpha, framebuffer);
+    }
+}
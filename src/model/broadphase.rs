@@ -0,0 +1,143 @@
+use super::*;
+
+use std::collections::HashMap;
+
+/// Size of a grid cell, roughly the size of the biggest entities.
+const CELL_SIZE: f32 = 2.0;
+
+/// A uniform grid over the cylinder to quickly find the colliders that might intersect.
+/// Colliders are inserted with the area swept since the previous tick,
+/// so fast bodies are found along their whole path.
+/// Colliders crossing the seam are split by `Collider::swept_world_aabbs`,
+/// so they are found from both sides.
+///
+/// Entries are identified by their index in the list they were built from,
+/// and queries return them in that order, so the results do not depend on hashing.
+pub struct Broadphase {
+    columns: usize,
+    cell_width: Coord,
+    cell_height: Coord,
+    cells: HashMap<(usize, i64), Vec<usize>>,
+}
+
+impl Broadphase {
+    pub fn new(world_width: Coord) -> Self {
+        let columns = (world_width.as_f32() / CELL_SIZE).floor().max(1.0) as usize;
+        Self {
+            columns,
+            cell_width: world_width / r32(columns as f32),
+            cell_height: r32(CELL_SIZE),
+            cells: HashMap::new(),
+        }
+    }
+
+    /// Build the grid from the colliders, identified by their index in the iterator.
+    pub fn build<'a>(
+        world_width: Coord,
+        colliders: impl IntoIterator<Item = &'a Collider>,
+    ) -> Self {
+        let mut grid = Self::new(world_width);
+        for (index, collider) in colliders.into_iter().enumerate() {
+            grid.insert(index, collider);
+        }
+        grid
+    }
+
+    pub fn insert(&mut self, index: usize, collider: &Collider) {
+        for aabb in collider.swept_world_aabbs() {
+            for cell in self.cells_overlapping(aabb) {
+                let entries = self.cells.entry(cell).or_default();
+                // Both parts of a collider crossing the seam might overlap the same cell
+                if entries.last() != Some(&index) {
+                    entries.push(index);
+                }
+            }
+        }
+    }
+
+    /// Returns the indices of the entries that might overlap the collider, sorted.
+    pub fn query(&self, collider: &Collider) -> Vec<usize> {
+        self.entries_in(collider.swept_world_aabbs())
+    }
+
+    /// Returns the indices of the entries that might be touched by a circle of `radius`
+    /// moving from `origin` by `motion`, sorted.
+    /// Only the cells along the segment are checked, not its whole bounding box.
+    pub fn query_segment(
+        &self,
+        origin: Position,
+        motion: vec2<Coord>,
+        radius: Coord,
+    ) -> Vec<usize> {
+        let start = origin.to_world();
+        // Steps no longer than a cell, so no cell the segment crosses is skipped
+        let steps = (motion.len() / self.cell_width.min(self.cell_height))
+            .ceil()
+            .as_f32()
+            .max(1.0) as usize;
+        let point = |step: usize| start + motion * r32(step as f32 / steps as f32);
+        self.entries_in((0..steps).flat_map(|step| {
+            let aabb = Aabb2::from_corners(point(step), point(step + 1)).extend_uniform(radius);
+            split_at_seam(aabb, origin.world_width())
+        }))
+    }
+
+    fn entries_in(&self, aabbs: impl Iterator<Item = Aabb2<Coord>>) -> Vec<usize> {
+        let mut result: Vec<usize> = aabbs
+            .flat_map(|aabb| self.cells_overlapping(aabb))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+        result.sort_unstable();
+        result.dedup();
+        result
+    }
+
+    /// Cells overlapping a box within `0..=world_width` horizontally.
+    fn cells_overlapping(&self, aabb: Aabb2<Coord>) -> impl Iterator<Item = (usize, i64)> {
+        let cell = |x: Coord, size: Coord| (x / size).floor().as_f32() as i64;
+        let column = |x: Coord| (cell(x, self.cell_width).max(0) as usize).min(self.columns - 1);
+        let (min_x, max_x) = (column(aabb.min.x), column(aabb.max.x));
+        let (min_y, max_y) = (
+            cell(aabb.min.y, self.cell_height),
+            cell(aabb.max.y, self.cell_height),
+        );
+        (min_x..=max_x).flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: f32 = 20.0;
+
+    fn collider(x: f32, y: f32) -> Collider {
+        let position = Position::from_world(vec2(x, y).as_r32(), r32(WIDTH));
+        Collider::new(position, Shape::rectangle(1.0, 1.0))
+    }
+
+    #[test]
+    fn finds_colliders_across_the_seam() {
+        // Sticks out over the right edge
+        let grid = Broadphase::build(r32(WIDTH), [&collider(WIDTH - 0.2, 0.0)]);
+        assert_eq!(grid.query(&collider(0.5, 0.0)), vec![0]);
+        assert!(grid.query(&collider(WIDTH / 2.0, 0.0)).is_empty());
+
+        let origin = Position::from_world(vec2(WIDTH - 3.0, 0.0).as_r32(), r32(WIDTH));
+        let motion = vec2(4.0, 0.0).as_r32();
+        assert_eq!(grid.query_segment(origin, motion, r32(0.1)), vec![0]);
+    }
+
+    #[test]
+    fn finds_moved_colliders_along_their_path() {
+        let mut moved = collider(2.0, 0.0);
+        moved.position = moved.position.shifted(vec2(6.0, 0.0).as_r32());
+        let grid = Broadphase::build(r32(WIDTH), [&moved, &collider(2.0, 10.0)]);
+
+        assert_eq!(grid.query(&collider(5.0, 0.0)), vec![0]);
+        assert_eq!(grid.query(&collider(2.0, 10.0)), vec![1]);
+        assert!(grid.query(&collider(14.0, 0.0)).is_empty());
+    }
+}
@@ -1,8 +1,10 @@
 use super::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Camera {
     pub center: Position,
+    /// Center at the previous simulation tick, used for render interpolation.
+    pub prev_center: Position,
     pub offset_center: vec2<Coord>,
     pub fov: Coord,
     pub target_position: Position,
@@ -14,6 +16,7 @@ impl Camera {
     pub fn new(fov: impl Float, world_width: Coord) -> Self {
         Self {
             center: Position::zero(world_width),
+            prev_center: Position::zero(world_width),
             offset_center: vec2::ZERO,
             fov: fov.as_r32(),
             target_position: Position::zero(world_width),
@@ -22,6 +25,15 @@ impl Camera {
         }
     }
 
+    /// Returns the camera with its center interpolated between
+    /// the previous and the current simulation tick.
+    pub fn interpolated(&self, t: Coord) -> Self {
+        Self {
+            center: self.prev_center.lerp(self.center, t),
+            ..self.clone()
+        }
+    }
+
     fn to_camera2d(&self) -> geng::Camera2d {
         geng::Camera2d {
             center: self.center.to_world().as_f32() + self.offset_center.as_f32(),
@@ -41,6 +53,45 @@ impl Camera {
         self.project(position).as_f32()
     }
 
+    /// The visible area relative to the camera, in the same space as `project`.
+    pub fn view_aabb(&self) -> Aabb2<Coord> {
+        let size = self.framebuffer_size.as_f32();
+        let aspect = r32(size.x / size.y.max(1.0));
+        let center = self.center.to_world() + self.offset_center;
+        Aabb2::point(center).extend_symmetric(vec2(self.fov * aspect, self.fov) / r32(2.0))
+    }
+
+    /// The visible area in world coordinates, split at the cylinder seam.
+    pub fn world_view_aabbs(&self) -> impl Iterator<Item = Aabb2<Coord>> {
+        split_at_seam(self.view_aabb(), self.center.world_width())
+    }
+
+    /// Whether any part of the collider is visible.
+    pub fn is_visible(&self, collider: &Collider) -> bool {
+        collider
+            .world_aabbs()
+            .any(|aabb| self.world_view_aabbs().any(|view| view.intersects(&aabb)))
+    }
+
+    /// Positions relative to the camera (see `project`) of the visible copies of the collider.
+    /// A collider crossing the seam near the edge of the view can be visible on both sides.
+    pub fn visible_images(&self, collider: &Collider) -> Vec<vec2<Coord>> {
+        if !self.is_visible(collider) {
+            return Vec::new();
+        }
+        let world_width = self.center.world_width();
+        let position = self.project(collider.position);
+        let local = collider
+            .compute_aabb()
+            .translate(-collider.position.to_world());
+        let view = self.view_aabb();
+        [-world_width, Coord::ZERO, world_width]
+            .into_iter()
+            .map(|shift| position + vec2(shift, Coord::ZERO))
+            .filter(|&image| local.translate(image).intersects(&view))
+            .collect()
+    }
+
     /// Returns the positions of the cursor in the world space.
     pub fn cursor_pos_world(&self) -> Position {
         let pos = self
@@ -0,0 +1,133 @@
+use super::*;
+
+/// A hand-authored segment of the level, spliced in between the random clouds.
+/// All positions are relative to the chunk's origin.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chunk {
+    pub name: String,
+    /// The lowest height at which the chunk can appear.
+    pub min_height: Coord,
+    /// The highest height at which the chunk can appear.
+    #[serde(default)]
+    pub max_height: Option<Coord>,
+    /// Chance to place the chunk instead of a random cloud.
+    pub chance: R32,
+    /// Vertical space taken by the chunk, random generation continues above it.
+    pub height: Coord,
+    #[serde(default)]
+    pub clouds: Vec<ChunkCloud>,
+    /// Free floating coins.
+    #[serde(default)]
+    pub coins: Vec<vec2<Coord>>,
+    #[serde(default)]
+    pub birds: Vec<ChunkBird>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkCloud {
+    pub position: vec2<Coord>,
+    /// Velocity of the cloud's anchor.
+    #[serde(default = "zero_velocity")]
+    pub velocity: vec2<Coord>,
+    /// Trigger attached to the cloud.
+    #[serde(default)]
+    pub trigger: Option<TriggerKind>,
+    /// Shape of the cloud, if different from the default one.
+    /// Polygon points can be listed in any order, see `ChunkCloud::shape`.
+    #[serde(default)]
+    pub shape: Option<Shape>,
+}
+
+impl ChunkCloud {
+    /// The shape to give the cloud, with the polygon points put in order.
+    /// Panics if the shape does not pass `Shape::validate`.
+    pub fn shape(&self) -> Option<Shape> {
+        self.shape.clone().map(|shape| match shape {
+            Shape::ConvexPolygon { points } => Shape::convex_polygon(points),
+            shape => shape,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkBird {
+    pub position: vec2<Coord>,
+    /// Horizontal speed, negative to fly left.
+    pub speed: Coord,
+    /// The bird spawns when the player reaches this height.
+    pub spawn_height: Coord,
+    #[serde(default)]
+    pub kind: BirdKind,
+}
+
+/// A bird waiting for the player to climb high enough to spawn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptedBird {
+    pub spawn_height: Coord,
+    pub position: Position,
+    pub speed: Coord,
+    pub kind: BirdKind,
+}
+
+impl Chunk {
+    /// Check that the chunk can be placed, see `Shape::validate`.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(self.height > Coord::ZERO, "height must be positive");
+        for (i, cloud) in self.clouds.iter().enumerate() {
+            if let Some(shape) = &cloud.shape {
+                shape
+                    .validate()
+                    .with_context(|| format!("invalid shape of cloud {i}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check whether the chunk can be placed at the given height.
+    pub fn fits(&self, height: Coord) -> bool {
+        height >= self.min_height && self.max_height.map_or(true, |max| height <= max)
+    }
+}
+
+fn zero_velocity() -> vec2<Coord> {
+    vec2::ZERO
+}
+
+/// Validate every chunk, reporting the name of the invalid one.
+pub fn validate_chunks(chunks: &[Chunk]) -> anyhow::Result<()> {
+    for chunk in chunks {
+        chunk
+            .validate()
+            .with_context(|| format!("invalid chunk {:?}", chunk.name))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn game_chunks_are_valid() {
+        let chunks: Vec<Chunk> = ron::from_str(include_str!("../../assets/chunks.ron")).unwrap();
+        validate_chunks(&chunks).unwrap();
+    }
+
+    #[test]
+    fn degenerate_polygon_is_rejected() {
+        let chunk: Chunk = ron::from_str(
+            r#"(
+                name: "Flat",
+                min_height: 0.0,
+                chance: 1.0,
+                height: 1.0,
+                clouds: [(
+                    position: (0.0, 0.0),
+                    shape: Some(ConvexPolygon(points: [(-1.0, 0.0), (0.0, 0.0), (1.0, 0.0)])),
+                )],
+            )"#,
+        )
+        .unwrap();
+        assert!(chunk.validate().is_err());
+    }
+}
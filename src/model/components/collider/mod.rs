@@ -12,19 +12,63 @@ pub struct Collision {
     pub penetration: Coord,
 }
 
+/// Where a ray or a shape cast hits a collider.
+#[derive(Debug, Clone, Copy)]
+pub struct CastHit {
+    /// Distance travelled along the cast direction before the hit.
+    pub distance: Coord,
+    pub point: Position,
+    /// Normal of the collider's surface at the hit point.
+    pub normal: vec2<Coord>,
+}
+
+/// The first contact of two moving colliders, see `Collider::sweep`.
+#[derive(Debug, Clone, Copy)]
+pub struct Impact {
+    /// Fraction of the tick at which the colliders touch, in `0..=1`.
+    pub time: R32,
+    /// Contact at the moment of impact, with zero penetration.
+    pub collision: Collision,
+}
+
 #[derive(SplitFields, Debug, Clone, Serialize, Deserialize)]
 pub struct Collider {
     pub position: Position,
+    /// Position at the previous simulation tick, used for render interpolation.
+    pub prev_position: Position,
     pub rotation: Angle<Coord>,
-    pub shape: Shape,
+    /// Changed only with `set_shape`, so that the cached parry shape is rebuilt.
+    shape: Shape,
+    #[serde(skip)]
+    shape_cache: ShapeCache,
 }
 
 impl Collider {
     pub fn new(position: Position, shape: Shape) -> Self {
         Self {
             position,
+            prev_position: position,
             rotation: Angle::ZERO,
             shape,
+            shape_cache: ShapeCache::default(),
+        }
+    }
+
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+
+    pub fn set_shape(&mut self, shape: Shape) {
+        self.shape = shape;
+        self.shape_cache = ShapeCache::default();
+    }
+
+    /// Returns the collider at the position interpolated between
+    /// the previous and the current simulation tick.
+    pub fn interpolated(&self, t: Coord) -> Self {
+        Self {
+            position: self.prev_position.lerp(self.position, t),
+            ..self.clone()
         }
     }
 
@@ -34,7 +78,8 @@ impl Collider {
     }
 
     /// NOTE: Use with caution, as it does not normalize distance to other entities.
-    /// So it should not be used in raw form for collisions or rendering.
+    /// So it should not be used in raw form for collisions or rendering,
+    /// use `world_aabbs` instead. Fine for measuring the size.
     pub fn compute_aabb(&self) -> Aabb2<Coord> {
         let (iso, shape) = self.to_parry();
         let parry2d::bounding_volume::Aabb { mins, maxs } = shape.compute_aabb(&iso);
@@ -44,6 +89,35 @@ impl Collider {
         }
     }
 
+    /// Bounding boxes in world coordinates, split at the cylinder seam.
+    /// Returns two boxes if the collider crosses the seam, one otherwise.
+    pub fn world_aabbs(&self) -> impl Iterator<Item = Aabb2<Coord>> {
+        split_at_seam(self.compute_aabb(), self.position.world_width())
+    }
+
+    /// Bounding boxes of the area covered by the collider since the previous tick,
+    /// split at the cylinder seam.
+    pub fn swept_world_aabbs(&self) -> impl Iterator<Item = Aabb2<Coord>> {
+        let aabb = self.compute_aabb();
+        let prev = aabb.translate(-self.motion());
+        let swept = Aabb2 {
+            min: vec2(aabb.min.x.min(prev.min.x), aabb.min.y.min(prev.min.y)),
+            max: vec2(aabb.max.x.max(prev.max.x), aabb.max.y.max(prev.max.y)),
+        };
+        split_at_seam(swept, self.position.world_width())
+    }
+
+    /// Displacement since the previous simulation tick.
+    pub fn motion(&self) -> vec2<Coord> {
+        self.prev_position.delta_to(self.position)
+    }
+
+    /// Position at the fraction `t` of the way from the previous tick to the current one.
+    /// Unlike `interpolated`, follows the actual motion across the seam.
+    pub fn position_at(&self, t: R32) -> Position {
+        self.prev_position.shifted(self.motion() * t)
+    }
+
     fn get_iso(&self) -> parry2d::math::Isometry<f32> {
         let vec2(x, y) = self.position.to_world_f32();
         let angle = self.rotation.as_radians().as_f32();
@@ -52,8 +126,13 @@ impl Collider {
 
     /// NOTE: Use with caution, as it does not normalize distance to other entities.
     /// So it should not be used in raw form for collisions or rendering.
-    fn to_parry(&self) -> (parry2d::math::Isometry<f32>, Box<dyn parry2d::shape::Shape>) {
-        (self.get_iso(), self.shape.to_parry())
+    fn to_parry(&self) -> (parry2d::math::Isometry<f32>, parry2d::shape::SharedShape) {
+        (self.get_iso(), self.parry_shape())
+    }
+
+    /// The parry shape, built once and reused for every check.
+    pub fn parry_shape(&self) -> parry2d::shape::SharedShape {
+        self.shape_cache.get(&self.shape)
     }
 
     /// Check whether two colliders are intersecting.
@@ -62,29 +141,37 @@ impl Collider {
 
         let self_angle = self.rotation.as_radians().as_f32();
         let self_iso = parry2d::math::Isometry::rotation(self_angle);
-        let self_shape = self.shape.to_parry();
+        let self_shape = self.parry_shape();
 
         let other_angle = other.rotation.as_radians().as_f32();
         let other_iso =
             parry2d::math::Isometry::new(parry2d::na::Vector2::new(delta.x, delta.y), other_angle);
-        let other_shape = other.shape.to_parry();
+        let other_shape = other.parry_shape();
 
         parry2d::query::intersection_test(&self_iso, &*self_shape, &other_iso, &*other_shape)
             .unwrap()
     }
 
+    /// The direction from which the collider is solid, if it is a one-way platform.
+    pub fn one_way_normal(&self) -> Option<vec2<Coord>> {
+        self.shape
+            .one_way_normal()
+            .map(|normal| normal.rotate(self.rotation))
+    }
+
     /// Return the collision info if the two colliders are intersecting.
+    /// One-way platforms only collide with the bodies on their solid side.
     pub fn collide(&self, other: &Self) -> Option<Collision> {
         let delta = self.position.delta_to(other.position).as_f32();
 
         let self_angle = self.rotation.as_radians().as_f32();
         let self_iso = parry2d::math::Isometry::rotation(self_angle);
-        let self_shape = self.shape.to_parry();
+        let self_shape = self.parry_shape();
 
         let other_angle = other.rotation.as_radians().as_f32();
         let other_iso =
             parry2d::math::Isometry::new(parry2d::na::Vector2::new(delta.x, delta.y), other_angle);
-        let other_shape = other.shape.to_parry();
+        let other_shape = other.parry_shape();
 
         let prediction = 0.0;
         parry2d::query::contact(
@@ -106,5 +193,208 @@ impl Collider {
                 penetration: Coord::new(-contact.dist),
             }
         })
+        .filter(|collision| self.is_solid_towards(other, collision.normal))
     }
+
+    /// Move both colliders from their previous positions to the current ones
+    /// and return the first moment they touch.
+    /// Catches fast bodies that pass through each other within a single tick,
+    /// which `collide` misses as it only checks the final positions.
+    /// Colliders that were already intersecting at the start are ignored.
+    pub fn sweep(&self, other: &Self) -> Option<Impact> {
+        let delta = self.prev_position.delta_to(other.prev_position).as_f32();
+        let self_motion = self.motion().as_f32();
+        let other_motion = other.motion().as_f32();
+
+        let self_angle = self.rotation.as_radians().as_f32();
+        let self_iso = parry2d::math::Isometry::rotation(self_angle);
+        let self_shape = self.parry_shape();
+
+        let other_angle = other.rotation.as_radians().as_f32();
+        let other_iso =
+            parry2d::math::Isometry::new(parry2d::na::Vector2::new(delta.x, delta.y), other_angle);
+        let other_shape = other.parry_shape();
+
+        let max_time = 1.0;
+        let impact = parry2d::query::time_of_impact(
+            &self_iso,
+            &parry2d::na::Vector2::new(self_motion.x, self_motion.y),
+            &*self_shape,
+            &other_iso,
+            &parry2d::na::Vector2::new(other_motion.x, other_motion.y),
+            &*other_shape,
+            max_time,
+            false,
+        )
+        .unwrap()?;
+        if impact.status == parry2d::query::TOIStatus::Penetrating {
+            return None;
+        }
+
+        // Witness and normal are local to the shape
+        let time = Coord::new(impact.toi);
+        let point = self_iso.rotation * impact.witness1;
+        let normal = self_iso.rotation * impact.normal1.into_inner();
+        let collision = Collision {
+            point: self
+                .position_at(time)
+                .shifted(vec2(point.x, point.y).map(Coord::new)),
+            normal: vec2(normal.x, normal.y).map(Coord::new),
+            penetration: Coord::ZERO,
+        };
+        self.is_solid_towards(other, collision.normal)
+            .then_some(Impact { time, collision })
+    }
+
+    /// Cast a ray from `origin` and return the first point where it hits the collider.
+    /// The ray wraps around the cylinder, so it can hit the collider from either side.
+    /// One-way platforms are only hit from their solid side.
+    pub fn cast_ray(
+        &self,
+        origin: Position,
+        direction: vec2<Coord>,
+        max_distance: Coord,
+    ) -> Option<CastHit> {
+        let direction = direction.normalize_or_zero();
+        if direction == vec2::ZERO || !self.is_hit_from(direction) {
+            return None;
+        }
+
+        let iso = parry2d::math::Isometry::rotation(self.rotation.as_radians().as_f32());
+        let shape = self.parry_shape();
+        let dir = direction.as_f32();
+        self.wrapped_starts(origin, direction * max_distance, 0.0)
+            .into_iter()
+            .filter_map(|start| {
+                let ray = parry2d::query::Ray::new(
+                    parry2d::math::Point::new(start.x, start.y),
+                    parry2d::na::Vector2::new(dir.x, dir.y),
+                );
+                let hit = shape.cast_ray_and_get_normal(&iso, &ray, max_distance.as_f32(), true)?;
+                Some((start + dir * hit.toi, hit.toi, hit.normal))
+            })
+            .min_by_key(|&(_, distance, _)| r32(distance))
+            .map(|(point, distance, normal)| CastHit {
+                distance: r32(distance),
+                point: self.position.shifted(point.as_r32()),
+                normal: vec2(normal.x, normal.y).as_r32(),
+            })
+    }
+
+    /// Move the `shape` from `origin` in the `direction` and return the first point
+    /// where it touches the collider, wrapping around the cylinder like `cast_ray`.
+    /// If the shape already intersects the collider at the origin, the hit is at zero distance.
+    pub fn cast_shape(
+        &self,
+        shape: &Shape,
+        origin: Position,
+        direction: vec2<Coord>,
+        max_distance: Coord,
+    ) -> Option<CastHit> {
+        let direction = direction.normalize_or_zero();
+        if direction == vec2::ZERO || !self.is_hit_from(direction) {
+            return None;
+        }
+
+        let iso = parry2d::math::Isometry::rotation(self.rotation.as_radians().as_f32());
+        let self_shape = self.parry_shape();
+        let cast_shape = shape.to_parry();
+        let cast_size = cast_shape.compute_local_aabb().extents().max();
+        let dir = direction.as_f32();
+        self.wrapped_starts(origin, direction * max_distance, cast_size)
+            .into_iter()
+            .filter_map(|start| {
+                let impact = parry2d::query::time_of_impact(
+                    &parry2d::math::Isometry::translation(start.x, start.y),
+                    &parry2d::na::Vector2::new(dir.x, dir.y),
+                    &*cast_shape,
+                    &iso,
+                    &parry2d::na::Vector2::zeros(),
+                    &*self_shape,
+                    max_distance.as_f32(),
+                    true,
+                )
+                .unwrap()?;
+                // Witness and normal are local to the shape
+                let point = iso.rotation * impact.witness2;
+                let normal = iso.rotation * impact.normal2.into_inner();
+                Some((vec2(point.x, point.y), impact.toi, vec2(normal.x, normal.y)))
+            })
+            .min_by_key(|&(_, distance, _)| r32(distance))
+            .map(|(point, distance, normal)| CastHit {
+                distance: r32(distance),
+                point: self.position.shifted(point.as_r32()),
+                normal: normal.as_r32(),
+            })
+    }
+
+    /// Starting points of a cast relative to the collider, one for each copy
+    /// of the collider around the cylinder that the cast might reach.
+    /// `margin` is the size of the cast shape.
+    fn wrapped_starts(&self, origin: Position, motion: vec2<Coord>, margin: f32) -> Vec<vec2<f32>> {
+        let world_width = self.position.world_width().as_f32();
+        let start = self.position.delta_to(origin).as_f32();
+        let motion = motion.as_f32();
+        let size = self.compute_aabb().size().as_f32();
+        let reach = size.x.max(size.y) + margin;
+
+        let (min_x, max_x) = (
+            start.x.min(start.x + motion.x),
+            start.x.max(start.x + motion.x),
+        );
+        let min_copy = ((min_x - reach) / world_width).floor() as i32;
+        let max_copy = ((max_x + reach) / world_width).ceil() as i32;
+        (min_copy..=max_copy)
+            .map(|copy| start - vec2(copy as f32 * world_width, 0.0))
+            .collect()
+    }
+
+    /// Whether a cast moving in the `direction` can hit the collider,
+    /// i.e. it is not coming from the back of a one-way platform.
+    fn is_hit_from(&self, direction: vec2<Coord>) -> bool {
+        self.one_way_normal()
+            .map_or(true, |one_way| vec2::dot(direction, one_way) < Coord::ZERO)
+    }
+
+    /// Whether the contact with the `normal` pointing from `self` towards `other`
+    /// is blocked, i.e. not passing through the back of a one-way platform.
+    fn is_solid_towards(&self, other: &Self, normal: vec2<Coord>) -> bool {
+        let solid_self = self
+            .one_way_normal()
+            .map_or(true, |one_way| vec2::dot(normal, one_way) > Coord::ZERO);
+        let solid_other = other
+            .one_way_normal()
+            .map_or(true, |one_way| vec2::dot(normal, one_way) < Coord::ZERO);
+        solid_self && solid_other
+    }
+}
+
+/// Split a box in world coordinates at the cylinder seam,
+/// so that each part lies within `0..=world_width` horizontally.
+/// Returns two boxes if it crosses the seam, one otherwise.
+pub fn split_at_seam(aabb: Aabb2<Coord>, world_width: Coord) -> impl Iterator<Item = Aabb2<Coord>> {
+    let width = aabb.width();
+    let with_x = |min_x: Coord, max_x: Coord| Aabb2 {
+        min: vec2(min_x, aabb.min.y),
+        max: vec2(max_x, aabb.max.y),
+    };
+    let parts = if width >= world_width {
+        // Wraps all the way around
+        [Some(with_x(Coord::ZERO, world_width)), None]
+    } else {
+        let mut min_x = Position::from_world(aabb.min, world_width).to_world().x;
+        if min_x >= world_width {
+            min_x -= world_width;
+        }
+        let max_x = min_x + width;
+        if max_x <= world_width {
+            [Some(with_x(min_x, max_x)), None]
+        } else {
+            [
+                Some(with_x(min_x, world_width)),
+                Some(with_x(Coord::ZERO, max_x - world_width)),
+            ]
+        }
+    };
+    parts.into_iter().flatten()
 }
@@ -1,9 +1,35 @@
 use super::*;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+use parry2d::shape::SharedShape;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Shape {
-    Circle { radius: Coord },
-    Rectangle { width: Coord, height: Coord },
+    Circle {
+        radius: Coord,
+    },
+    Rectangle {
+        width: Coord,
+        height: Coord,
+    },
+    /// A vertical capsule: a rectangle with half-circles on the top and the bottom.
+    Capsule {
+        /// Half the distance between the centers of the two half-circles.
+        half_height: Coord,
+        radius: Coord,
+    },
+    /// Vertices of a convex polygon in counter-clockwise order,
+    /// use `Shape::convex_polygon` to construct from arbitrary points.
+    ConvexPolygon {
+        points: Vec<vec2<Coord>>,
+    },
+    /// A line from `a` to `b`.
+    Segment {
+        a: vec2<Coord>,
+        b: vec2<Coord>,
+        /// A one-way platform is solid only from the left side of `a` to `b`,
+        /// i.e. from above for a segment going to the right.
+        one_way: bool,
+    },
 }
 
 impl Shape {
@@ -20,18 +46,118 @@ impl Shape {
         }
     }
 
-    pub fn to_parry(self) -> Box<dyn parry2d::shape::Shape> {
+    pub fn capsule(half_height: impl Float, radius: impl Float) -> Self {
+        Self::Capsule {
+            half_height: half_height.as_r32(),
+            radius: radius.as_r32(),
+        }
+    }
+
+    /// The convex hull of the points.
+    /// Panics if the points do not form a polygon, i.e. there are less than three
+    /// or they all lie on a single line.
+    pub fn convex_polygon(points: impl IntoIterator<Item = vec2<Coord>>) -> Self {
+        let points: Vec<_> = points.into_iter().collect();
+        let hull = Self::hull(&points)
+            .unwrap_or_else(|| panic!("points {points:?} do not form a polygon"));
+        let polygon = hull.as_convex_polygon().expect("hull is a convex polygon");
+        Self::ConvexPolygon {
+            points: polygon
+                .points()
+                .iter()
+                .map(|p| vec2(p.x, p.y).as_r32())
+                .collect(),
+        }
+    }
+
+    /// The direction from which a one-way shape is solid.
+    pub fn one_way_normal(&self) -> Option<vec2<Coord>> {
+        match *self {
+            Self::Segment {
+                a,
+                b,
+                one_way: true,
+            } => Some((b - a).rotate_90().normalize_or_zero()),
+            _ => None,
+        }
+    }
+
+    /// Check that the shape is not degenerate, so that it can be turned into a parry shape.
+    pub fn validate(&self) -> anyhow::Result<()> {
         match self {
-            Shape::Circle { radius } => Box::new(parry2d::shape::Ball::new(radius.as_f32())),
+            Self::Circle { radius } => {
+                anyhow::ensure!(*radius > Coord::ZERO, "circle radius must be positive");
+            }
+            Self::Rectangle { width, height } => {
+                anyhow::ensure!(
+                    *width > Coord::ZERO && *height > Coord::ZERO,
+                    "rectangle size must be positive"
+                );
+            }
+            Self::Capsule {
+                half_height,
+                radius,
+            } => {
+                anyhow::ensure!(
+                    *half_height >= Coord::ZERO && *radius > Coord::ZERO,
+                    "capsule size must be positive"
+                );
+            }
+            Self::ConvexPolygon { points } => {
+                anyhow::ensure!(
+                    Self::hull(points).is_some(),
+                    "points {points:?} do not form a polygon"
+                );
+            }
+            Self::Segment { a, b, .. } => {
+                anyhow::ensure!(a != b, "segment ends must be different");
+            }
+        }
+        Ok(())
+    }
+
+    fn hull(points: &[vec2<Coord>]) -> Option<SharedShape> {
+        let points: Vec<_> = points
+            .iter()
+            .map(|p| parry2d::math::Point::new(p.x.as_f32(), p.y.as_f32()))
+            .collect();
+        SharedShape::convex_hull(&points)
+    }
+
+    /// Build the parry shape. Prefer `Collider::parry_shape`, which caches the result.
+    /// Panics if the shape does not pass `Shape::validate`.
+    pub fn to_parry(&self) -> SharedShape {
+        let point = |p: vec2<Coord>| parry2d::math::Point::new(p.x.as_f32(), p.y.as_f32());
+        match *self {
+            Shape::Circle { radius } => SharedShape::ball(radius.as_f32()),
             Shape::Rectangle { width, height } => {
-                let aabb = Aabb2::ZERO.extend_symmetric(vec2(width, height).as_f32() / 2.0);
-                let points = aabb.corners().map(|p| {
-                    let vec2(x, y) = p;
-                    parry2d::math::Point::new(x, y)
-                });
-                // All rectangles are convex
-                Box::new(parry2d::shape::ConvexPolygon::from_convex_hull(&points).unwrap())
+                SharedShape::cuboid(width.as_f32() / 2.0, height.as_f32() / 2.0)
+            }
+            Shape::Capsule {
+                half_height,
+                radius,
+            } => SharedShape::capsule_y(half_height.as_f32(), radius.as_f32()),
+            Shape::ConvexPolygon { ref points } => {
+                Self::hull(points).expect("convex polygon must have at least three points")
             }
+            Shape::Segment { a, b, .. } => SharedShape::segment(point(a), point(b)),
         }
     }
 }
+
+/// Lazily built parry shape of a collider.
+/// Has to be reset whenever the shape changes, see `Collider::set_shape`.
+#[derive(Clone, Default)]
+pub struct ShapeCache(std::cell::OnceCell<SharedShape>);
+
+impl ShapeCache {
+    pub fn get(&self, shape: &Shape) -> SharedShape {
+        self.0.get_or_init(|| shape.to_parry()).clone()
+    }
+}
+
+impl std::fmt::Debug for ShapeCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShapeCache").finish_non_exhaustive()
+    }
+}
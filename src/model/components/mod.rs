@@ -1,13 +1,14 @@
 mod collider;
 mod position;
+mod power_up;
 
-pub use self::{collider::*, position::*};
+pub use self::{collider::*, position::*, power_up::*};
 
 use super::*;
 
 pub type Lifetime = geng_utils::bounded::Bounded<Time>;
 
-#[derive(SplitFields)]
+#[derive(SplitFields, Debug, Clone, Serialize, Deserialize)]
 pub struct Body {
     #[split(nested)]
     pub collider: Collider,
@@ -25,37 +26,17 @@ impl Body {
     }
 }
 
-// Nertsal: why do all compoennts need to be Clone + Debug???????????
-#[derive(Copy, Clone)]
-pub struct Instant {
-    inner: time::Instant,
-}
-
-impl Instant {
-    pub fn now() -> Self {
-        Self {
-            inner: time::Instant::now(),
-        }
-    }
-    pub fn elapsed(&self) -> time::Duration {
-        self.inner.elapsed()
-    }
-}
-
-impl std::fmt::Debug for Instant {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "instant :)")
-    }
-}
-
-#[derive(SplitFields)]
+#[derive(SplitFields, Debug, Clone)]
 pub struct Doodle {
     #[split(nested)]
     pub body: Body,
     pub grounded: Option<Id>,
     pub active_triggers: Vec<Id>,
-    pub coyote_timer: Instant,
-    pub shoot_timer: Instant,
+    /// Time left until the doodle stops being grounded after leaving a cloud.
+    pub coyote_time: Time,
+    /// Time left until the doodle can shoot again.
+    pub shoot_cooldown: Time,
+    pub power_ups: PowerUps,
 }
 
 impl Doodle {
@@ -64,13 +45,14 @@ impl Doodle {
             body,
             grounded: None,
             active_triggers: Vec::new(),
-            coyote_timer: Instant::now(),
-            shoot_timer: Instant::now(),
+            coyote_time: Time::ZERO,
+            shoot_cooldown: Time::ZERO,
+            power_ups: PowerUps::default(),
         }
     }
 }
 
-#[derive(SplitFields)]
+#[derive(SplitFields, Debug, Clone, Serialize, Deserialize)]
 pub struct Cloud {
     #[split(nested)]
     pub body: Body,
@@ -89,27 +71,67 @@ impl Cloud {
     }
 }
 
-#[derive(SplitFields)]
+#[derive(SplitFields, Debug, Clone, Serialize, Deserialize)]
 pub struct Projectile {
     #[split(nested)]
     pub body: Body,
     pub lifetime: Lifetime,
+    /// Index of the player that shot it.
+    pub owner: usize,
 }
 
 impl Projectile {
-    pub fn new(body: Body, lifetime: impl Float) -> Self {
+    pub fn new(body: Body, lifetime: impl Float, owner: usize) -> Self {
         Self {
             body,
             lifetime: Lifetime::new_max(lifetime.as_r32()),
+            owner,
         }
     }
 }
 
-#[derive(SplitFields)]
+#[derive(SplitFields, Debug, Clone, Serialize, Deserialize)]
 pub struct Bird {
     #[split(nested)]
     pub body: Body,
     pub lifetime: Lifetime,
+    pub kind: BirdKind,
+    /// Hits the bird can take before dying.
+    pub health: u32,
+    /// Time since the bird spawned.
+    pub age: Time,
+    /// Whether a diver has started its dive.
+    pub diving: bool,
+}
+
+/// How a bird flies, see `Model::bird_behaviour`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BirdKind {
+    /// Flies in a straight horizontal line.
+    #[default]
+    Straight,
+    /// Flies horizontally until above a doodle, then swoops at it.
+    Diver,
+    /// Bobs up and down while flying horizontally.
+    SineWave,
+    /// Steers towards the closest doodle.
+    Homing,
+    /// Flies slowly and takes several hits.
+    Armoured,
+}
+
+impl BirdKind {
+    /// Tint of the bird's sprite.
+    pub fn color(self) -> Color {
+        let hex = match self {
+            Self::Straight => "#FFFFFF",
+            Self::Diver => "#F29B9B",
+            Self::SineWave => "#A4E3A8",
+            Self::Homing => "#D9A4F2",
+            Self::Armoured => "#8C8C99",
+        };
+        Color::try_from(hex).unwrap()
+    }
 }
 
 impl Bird {
@@ -119,11 +141,25 @@ impl Bird {
         Self {
             body,
             lifetime: Lifetime::new_max(r32(5.0)),
+            kind: BirdKind::Straight,
+            health: 1,
+            age: Time::ZERO,
+            diving: false,
+        }
+    }
+
+    pub fn with_kind(mut self, kind: BirdKind, config: &BirdConfig) -> Self {
+        self.kind = kind;
+        if let BirdKind::Armoured = kind {
+            self.health = config.armoured_health;
+            self.body.velocity *= config.armoured_speed;
+            self.body.mass *= r32(3.0);
         }
+        self
     }
 }
 
-#[derive(SplitFields)]
+#[derive(SplitFields, Debug, Clone)]
 pub struct Trigger {
     pub kind: TriggerKind,
     #[split(nested)]
@@ -132,10 +168,11 @@ pub struct Trigger {
     pub attached_to: Option<Attachment>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TriggerKind {
     Spring,
     Coin,
+    PowerUp(PowerUp),
 }
 
 #[derive(Debug, Clone)]
@@ -145,34 +182,66 @@ pub struct Attachment {
 }
 
 impl Trigger {
-    pub fn spring(cloud: Id, world_width: Coord) -> Self {
-        let position = Position::zero(world_width);
-        let collider = Collider::new(position, Shape::rectangle(0.4, 0.4));
+    /// A trigger attached to the cloud at `cloud_position`,
+    /// placed right away, so it does not fly in from the origin on the first tick.
+    fn attached(
+        kind: TriggerKind,
+        shape: Shape,
+        cloud: Id,
+        cloud_position: Position,
+        relative_pos: vec2<Coord>,
+    ) -> Self {
         Self {
-            kind: TriggerKind::Spring,
-            collider,
+            kind,
+            collider: Collider::new(cloud_position.shifted(relative_pos), shape),
             attached_to: Some(Attachment {
-                relative_pos: vec2(0.0, 0.3).as_r32(),
+                relative_pos,
                 cloud,
             }),
         }
     }
 
-    pub fn coin(cloud: Option<Id>, world_width: Coord) -> Self {
-        let position = Position::zero(world_width);
-        let collider = Collider::new(position, Shape::circle(0.3));
+    pub fn spring(cloud: Id, cloud_position: Position) -> Self {
+        Self::attached(
+            TriggerKind::Spring,
+            Shape::rectangle(0.4, 0.4),
+            cloud,
+            cloud_position,
+            vec2(0.0, 0.3).as_r32(),
+        )
+    }
+
+    pub fn coin(cloud: Id, cloud_position: Position) -> Self {
+        Self::attached(
+            TriggerKind::Coin,
+            Shape::circle(0.3),
+            cloud,
+            cloud_position,
+            vec2(0.0, 0.5).as_r32(),
+        )
+    }
+
+    /// A coin floating in the air on its own.
+    pub fn free_coin(position: Position) -> Self {
         Self {
             kind: TriggerKind::Coin,
-            collider,
-            attached_to: cloud.map(|cloud| Attachment {
-                relative_pos: vec2(0.0, 0.5).as_r32(),
-                cloud,
-            }),
+            collider: Collider::new(position, Shape::circle(0.3)),
+            attached_to: None,
         }
     }
+
+    pub fn power_up(kind: PowerUp, cloud: Id, cloud_position: Position) -> Self {
+        Self::attached(
+            TriggerKind::PowerUp(kind),
+            Shape::circle(0.3),
+            cloud,
+            cloud_position,
+            vec2(0.0, 0.6).as_r32(),
+        )
+    }
 }
 
-#[derive(SplitFields)]
+#[derive(SplitFields, Debug, Clone, Serialize, Deserialize)]
 pub struct Particle {
     #[split(nested)]
     pub body: Body,
@@ -69,6 +69,12 @@ impl<T: Num> PositionCylinder<T> {
 
         delta
     }
+
+    /// Linearly interpolate from `self` to `towards`,
+    /// going the short way around the cylinder.
+    pub fn lerp(self, towards: Self, t: T) -> Self {
+        self.shifted(self.delta_to(towards) * t)
+    }
 }
 
 impl<T: Float> PositionCylinder<T> {
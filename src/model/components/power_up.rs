@@ -0,0 +1,94 @@
+use super::*;
+
+/// A temporary effect on the doodle, picked up from a trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PowerUp {
+    /// Sustained upward thrust.
+    Jetpack,
+    /// Absorbs one bird hit.
+    Shield,
+    /// Pulls nearby coins in.
+    Magnet,
+    /// Shorter shooting cooldown.
+    RapidFire,
+    /// Limits the falling speed.
+    FeatherFall,
+}
+
+impl PowerUp {
+    pub const ALL: [Self; 5] = [
+        Self::Jetpack,
+        Self::Shield,
+        Self::Magnet,
+        Self::RapidFire,
+        Self::FeatherFall,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Jetpack => "Jetpack",
+            Self::Shield => "Shield",
+            Self::Magnet => "Magnet",
+            Self::RapidFire => "Rapid fire",
+            Self::FeatherFall => "Feather fall",
+        }
+    }
+
+    /// Color of the pickup, its particles and the HUD.
+    pub fn color(self) -> Color {
+        let hex = match self {
+            Self::Jetpack => "#E0603A",
+            Self::Shield => "#3AA6E0",
+            Self::Magnet => "#C43AE0",
+            Self::RapidFire => "#E0C23A",
+            Self::FeatherFall => "#6FCF7C",
+        };
+        Color::try_from(hex).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ActivePowerUp {
+    pub kind: PowerUp,
+    pub time_left: Time,
+    pub duration: Time,
+}
+
+/// Power-ups active on a doodle, with the time left for each.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PowerUps(Vec<ActivePowerUp>);
+
+impl PowerUps {
+    pub fn is_active(&self, kind: PowerUp) -> bool {
+        self.0.iter().any(|active| active.kind == kind)
+    }
+
+    /// Activate the power-up, or restart its timer if it is already active.
+    pub fn activate(&mut self, kind: PowerUp, duration: Time) {
+        self.remove(kind);
+        self.0.push(ActivePowerUp {
+            kind,
+            time_left: duration,
+            duration,
+        });
+    }
+
+    /// Returns whether the power-up was active.
+    pub fn remove(&mut self, kind: PowerUp) -> bool {
+        let len = self.0.len();
+        self.0.retain(|active| active.kind != kind);
+        self.0.len() != len
+    }
+
+    /// Run down the timers and remove the expired power-ups.
+    pub fn tick(&mut self, delta_time: Time) {
+        for active in &mut self.0 {
+            active.time_left -= delta_time;
+        }
+        self.0.retain(|active| active.time_left > Time::ZERO);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ActivePowerUp> {
+        self.0.iter()
+    }
+}
@@ -0,0 +1,275 @@
+use super::*;
+
+use std::ops::RangeInclusive;
+
+/// Tuning parameters of the game, loaded from `assets/config.ron`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Downward acceleration applied to doodles.
+    pub gravity: Coord,
+    pub doodle: DoodleConfig,
+    pub cloud: CloudConfig,
+    pub power_up: PowerUpConfig,
+    pub bird: BirdConfig,
+    pub generation: GenerationConfig,
+}
+
+impl Config {
+    /// Check that the values make sense,
+    /// so a typo in the config does not crash the game mid-run.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(self.gravity > Coord::ZERO, "gravity must be positive");
+        // The jump arc divides by these, and generation relies on it to climb
+        let doodle = &self.doodle;
+        let positive = [
+            ("max_speed", doodle.max_speed),
+            ("move_speed", doodle.move_speed),
+            ("acceleration", doodle.acceleration),
+            ("jump", doodle.jump),
+        ];
+        for (name, value) in positive {
+            anyhow::ensure!(
+                value.as_f32().is_finite() && value > Coord::ZERO,
+                "doodle.{name} must be positive, got {value}"
+            );
+        }
+        let generation = &self.generation;
+        anyhow::ensure!(
+            generation.jump_safety > R32::ZERO && generation.jump_safety <= R32::ONE,
+            "generation.jump_safety must be in (0, 1], got {}",
+            generation.jump_safety
+        );
+        anyhow::ensure!(
+            generation.generate_ahead > Coord::ZERO,
+            "generation.generate_ahead must be positive"
+        );
+        anyhow::ensure!(
+            generation.max_wait > Time::ZERO,
+            "generation.max_wait must be positive"
+        );
+        let ranges = [
+            ("cloud_gap", &generation.cloud_gap, true),
+            ("moving_cloud_speed", &generation.moving_cloud_speed, false),
+            ("bird_interval", &generation.bird_interval, true),
+            ("bird_speed", &generation.bird_speed, false),
+        ];
+        for (name, curve, positive) in ranges {
+            for range in curve.values() {
+                anyhow::ensure!(
+                    range.start() <= range.end(),
+                    "generation.{name}: range start {} is greater than its end {}",
+                    range.start(),
+                    range.end()
+                );
+                // Zero would generate infinitely many clouds or birds at once
+                anyhow::ensure!(
+                    !positive || *range.start() > R32::ZERO,
+                    "generation.{name}: range must be positive, got {}..={}",
+                    range.start(),
+                    range.end()
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoodleConfig {
+    pub max_speed: Coord,
+    /// Horizontal speed the doodle accelerates towards when moving.
+    pub move_speed: Coord,
+    pub acceleration: Coord,
+    pub jump: Coord,
+    /// For how long after leaving a cloud the doodle can still jump.
+    pub coyote_time: Time,
+    /// Impulse given by a spring.
+    pub spring_jump: Coord,
+    /// Minimum vertical speed after bouncing off a spring.
+    pub spring_min_speed: Coord,
+    pub shoot_cooldown: Time,
+    pub projectile_speed: Coord,
+    pub projectile_lifetime: Time,
+    /// Impulse applied in the opposite direction of a shot.
+    pub recoil: Coord,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloudConfig {
+    /// How strongly the cloud is pulled back to its anchor.
+    pub elasticity: R32,
+    pub damping: R32,
+    /// Maximum distance the cloud can be pushed away from its anchor.
+    pub max_offset: Coord,
+    /// Maximum speed at which a doodle can push the cloud down.
+    pub push_speed: Coord,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerUpConfig {
+    pub jetpack_duration: Time,
+    /// Vertical speed the jetpack keeps the doodle at.
+    pub jetpack_speed: Coord,
+    pub shield_duration: Time,
+    pub magnet_duration: Time,
+    /// Distance from which coins are pulled in.
+    pub magnet_radius: Coord,
+    pub magnet_speed: Coord,
+    pub rapid_fire_duration: Time,
+    /// Shooting cooldown with rapid fire.
+    pub rapid_fire_cooldown: Time,
+    pub feather_fall_duration: Time,
+    /// Maximum falling speed with feather fall.
+    pub feather_fall_speed: Coord,
+}
+
+impl PowerUpConfig {
+    pub fn duration(&self, kind: PowerUp) -> Time {
+        match kind {
+            PowerUp::Jetpack => self.jetpack_duration,
+            PowerUp::Shield => self.shield_duration,
+            PowerUp::Magnet => self.magnet_duration,
+            PowerUp::RapidFire => self.rapid_fire_duration,
+            PowerUp::FeatherFall => self.feather_fall_duration,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BirdConfig {
+    /// Horizontal distance to a doodle below at which a diver starts its dive.
+    pub dive_distance: Coord,
+    pub dive_speed: Coord,
+    pub sine_amplitude: Coord,
+    /// Angular frequency of the sine-wave flight, in radians per second.
+    pub sine_frequency: R32,
+    pub homing_speed: Coord,
+    /// How quickly a homing bird changes its velocity.
+    pub homing_acceleration: Coord,
+    pub armoured_health: u32,
+    /// Speed multiplier of armoured birds.
+    pub armoured_speed: R32,
+}
+
+/// Relative chances of the bird kinds to spawn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BirdWeights {
+    pub straight: R32,
+    pub diver: R32,
+    pub sine_wave: R32,
+    pub homing: R32,
+    pub armoured: R32,
+}
+
+impl BirdWeights {
+    /// Pick a random bird kind according to the weights.
+    pub fn pick(&self, rng: &mut impl Rng) -> BirdKind {
+        let options = [
+            (BirdKind::Straight, self.straight),
+            (BirdKind::Diver, self.diver),
+            (BirdKind::SineWave, self.sine_wave),
+            (BirdKind::Homing, self.homing),
+            (BirdKind::Armoured, self.armoured),
+        ];
+        options
+            .choose_weighted(rng, |(_, weight)| weight.as_f32())
+            .map_or(BirdKind::Straight, |(kind, _)| *kind)
+    }
+}
+
+impl Interpolate for BirdWeights {
+    fn interpolate(&self, other: &Self, t: R32) -> Self {
+        Self {
+            straight: self.straight.interpolate(&other.straight, t),
+            diver: self.diver.interpolate(&other.diver, t),
+            sine_wave: self.sine_wave.interpolate(&other.sine_wave, t),
+            homing: self.homing.interpolate(&other.homing, t),
+            armoured: self.armoured.interpolate(&other.armoured, t),
+        }
+    }
+}
+
+/// Parameters of the level generation.
+/// Most of them are curves over height, so the game gets harder as the player climbs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationConfig {
+    /// How far above the player the level is generated.
+    pub generate_ahead: Coord,
+    /// Vertical distance between consecutive clouds.
+    pub cloud_gap: Curve<RangeInclusive<Coord>>,
+    pub moving_cloud_chance: Curve<R32>,
+    pub moving_cloud_speed: Curve<RangeInclusive<Coord>>,
+    pub spring_chance: Curve<R32>,
+    /// Chance for a cloud without a spring to have a power-up.
+    pub power_up_chance: Curve<R32>,
+    /// Chance for a cloud without a spring or a power-up to have a coin.
+    pub coin_chance: Curve<R32>,
+    /// Fraction of the theoretical jump reach that generated layouts may require.
+    pub jump_safety: R32,
+    /// How long the player can be expected to wait on a cloud
+    /// for a moving cloud to come into reach.
+    pub max_wait: Time,
+    /// The height from which birds start spawning.
+    pub bird_height: Coord,
+    pub bird_interval: Curve<RangeInclusive<Time>>,
+    pub bird_speed: Curve<RangeInclusive<Coord>>,
+    pub bird_kinds: Curve<BirdWeights>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        ron::from_str(include_str!("../../assets/config.ron")).unwrap()
+    }
+
+    #[test]
+    fn game_config_is_valid() {
+        config().validate().unwrap();
+    }
+
+    #[test]
+    fn reversed_range_is_rejected() {
+        let mut config = config();
+        config.generation.bird_speed = ron::from_str("[(0.0, (start: 6.0, end: 4.0))]").unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn zero_max_speed_is_rejected() {
+        let mut config = config();
+        config.doodle.max_speed = r32(0.0);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn zero_move_speed_is_rejected() {
+        let mut config = config();
+        config.doodle.move_speed = r32(0.0);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn zero_acceleration_is_rejected() {
+        let mut config = config();
+        config.doodle.acceleration = r32(0.0);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn negative_jump_is_rejected() {
+        let mut config = config();
+        config.doodle.jump = r32(-5.0);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn jump_safety_out_of_range_is_rejected() {
+        for safety in [0.0, -0.5, 1.5] {
+            let mut config = config();
+            config.generation.jump_safety = r32(safety);
+            assert!(config.validate().is_err(), "jump_safety {safety}");
+        }
+    }
+}
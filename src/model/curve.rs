@@ -0,0 +1,108 @@
+use super::*;
+
+use std::ops::RangeInclusive;
+
+/// A value that changes with height.
+/// Linearly interpolated between the keys, and constant outside of them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    into = "Vec<(Coord, T)>",
+    try_from = "Vec<(Coord, T)>",
+    bound(
+        serialize = "T: Clone + Serialize",
+        deserialize = "T: Deserialize<'de>"
+    )
+)]
+pub struct Curve<T> {
+    /// Pairs of height and value at that height, sorted by height.
+    keys: Vec<(Coord, T)>,
+}
+
+impl<T> TryFrom<Vec<(Coord, T)>> for Curve<T> {
+    type Error = String;
+
+    fn try_from(keys: Vec<(Coord, T)>) -> Result<Self, Self::Error> {
+        if keys.is_empty() {
+            return Err("curve has no keys".to_string());
+        }
+        for ((from, _), (to, _)) in keys.iter().zip(keys.iter().skip(1)) {
+            if from >= to {
+                return Err(format!(
+                    "curve keys must be sorted by height without duplicates, found {from} before {to}"
+                ));
+            }
+        }
+        Ok(Self { keys })
+    }
+}
+
+impl<T> From<Curve<T>> for Vec<(Coord, T)> {
+    fn from(curve: Curve<T>) -> Self {
+        curve.keys
+    }
+}
+
+pub trait Interpolate: Clone {
+    fn interpolate(&self, other: &Self, t: R32) -> Self;
+}
+
+impl Interpolate for R32 {
+    fn interpolate(&self, other: &Self, t: R32) -> Self {
+        *self + (*other - *self) * t
+    }
+}
+
+impl Interpolate for RangeInclusive<R32> {
+    fn interpolate(&self, other: &Self, t: R32) -> Self {
+        let start = self.start().interpolate(other.start(), t);
+        let end = self.end().interpolate(other.end(), t);
+        start..=end
+    }
+}
+
+impl<T> Curve<T> {
+    /// The values at every key.
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.keys.iter().map(|(_, value)| value)
+    }
+}
+
+impl<T: Interpolate> Curve<T> {
+    /// Evaluate the curve at the given height.
+    pub fn get(&self, height: Coord) -> T {
+        let i = self.keys.partition_point(|(key, _)| *key <= height);
+        if i == 0 {
+            return self.keys[0].1.clone();
+        }
+        let (from_height, from) = &self.keys[i - 1];
+        let Some((to_height, to)) = self.keys.get(i) else {
+            return from.clone();
+        };
+        let t = (height - *from_height) / (*to_height - *from_height);
+        from.interpolate(to, t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(keys: &str) -> Result<Curve<R32>, ron::error::SpannedError> {
+        ron::from_str(keys)
+    }
+
+    #[test]
+    fn interpolates_between_keys() {
+        let curve = parse("[(0.0, 1.0), (10.0, 3.0)]").unwrap();
+        assert_eq!(curve.get(r32(-5.0)), r32(1.0));
+        assert_eq!(curve.get(r32(5.0)), r32(2.0));
+        assert_eq!(curve.get(r32(20.0)), r32(3.0));
+    }
+
+    #[test]
+    fn rejects_bad_keys() {
+        assert!(parse("[]").is_err());
+        assert!(parse("[(10.0, 1.0), (0.0, 3.0)]").is_err());
+        assert!(parse("[(0.0, 1.0), (0.0, 3.0)]").is_err());
+    }
+}
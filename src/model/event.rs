@@ -0,0 +1,64 @@
+use super::*;
+
+/// Something that happened in the game during a tick.
+/// Audio, particles, score and anything else can react to events
+/// without the gameplay logic knowing about them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    LandedOnCloud {
+        doodle: Id,
+        cloud: Id,
+        position: Position,
+        /// Vertical speed relative to the cloud.
+        speed: Coord,
+    },
+    Jumped {
+        doodle: Id,
+        position: Position,
+    },
+    SpringBounced {
+        doodle: Id,
+        position: Position,
+    },
+    CoinCollected {
+        doodle: Id,
+        position: Position,
+    },
+    PowerUpCollected {
+        doodle: Id,
+        kind: PowerUp,
+        position: Position,
+    },
+    /// A bird hit a doodle with a shield, which absorbed the hit.
+    ShieldBroken {
+        doodle: Id,
+        position: Position,
+    },
+    BirdHitPlayer {
+        doodle: Id,
+        position: Position,
+        bird_velocity: vec2<Coord>,
+    },
+    /// A projectile hit a bird that survived the hit.
+    BirdWounded {
+        /// Index of the player that shot the bird.
+        player: usize,
+        position: Position,
+        projectile_velocity: vec2<Coord>,
+    },
+    BirdKilled {
+        /// Index of the player that shot the bird.
+        player: usize,
+        position: Position,
+        projectile_velocity: vec2<Coord>,
+    },
+    ShotFired {
+        doodle: Id,
+        position: Position,
+        velocity: vec2<Coord>,
+    },
+    Died {
+        doodle: Id,
+        score: i32,
+    },
+}
@@ -0,0 +1,270 @@
+use super::*;
+
+impl Model {
+    /// Steer the birds according to their kind.
+    pub fn bird_behaviour(&mut self, delta_time: Time) {
+        let config = &self.config.bird;
+        let doodles: Vec<Position> = query!(self.doodles, (&body.collider.position))
+            .map(|(_, (&position,))| position)
+            .collect();
+
+        for id in self.birds.ids() {
+            let (&kind, &position, &diving) =
+                get!(self.birds, id, (&kind, &body.collider.position, &diving)).unwrap();
+
+            // Goes the short way around the cylinder, so birds follow across the seam
+            let target = doodles
+                .iter()
+                .map(|&doodle| position.delta_to(doodle))
+                .min_by_key(|delta| delta.len());
+
+            // Divers only dive at doodles they can see through the clouds
+            let dive = kind == BirdKind::Diver
+                && !diving
+                && target.map_or(false, |delta| {
+                    delta.y < Coord::ZERO
+                        && delta.x.abs() < config.dive_distance
+                        && self
+                            .raycast(
+                                position,
+                                delta,
+                                delta.len(),
+                                QueryFilter::only(&[EntityKind::Cloud]),
+                            )
+                            .is_none()
+                });
+
+            let (velocity, age, diving) =
+                get!(self.birds, id, (&mut body.velocity, &mut age, &mut diving)).unwrap();
+            *age += delta_time;
+
+            match kind {
+                BirdKind::Straight | BirdKind::Armoured => {}
+                BirdKind::Diver => {
+                    if let Some(delta) = target.filter(|_| dive) {
+                        *diving = true;
+                        *velocity = delta.normalize_or_zero() * config.dive_speed;
+                    }
+                }
+                BirdKind::SineWave => {
+                    let phase = config.sine_frequency * *age;
+                    velocity.y = config.sine_amplitude * config.sine_frequency * phase.cos();
+                }
+                BirdKind::Homing => {
+                    if let Some(delta) = target {
+                        let target_velocity = delta.normalize_or_zero() * config.homing_speed;
+                        *velocity += (target_velocity - *velocity)
+                            .clamp_len(..=config.homing_acceleration * delta_time);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand_chacha::ChaCha8Rng;
+
+    const DELTA_TIME: f32 = 1.0 / 120.0;
+
+    /// A model with a single bird of the kind, and the doodle at the origin.
+    fn bird_model(kind: BirdKind, position: vec2<f32>, speed: f32) -> (Model, Id) {
+        let mut model = test_model(0);
+        let position = Position::from_world(position.as_r32(), model.world_width);
+        let bird = model
+            .birds
+            .insert(Bird::new(position, speed).with_kind(kind, &model.config.bird));
+        (model, bird)
+    }
+
+    /// Steer and move the birds for the given time.
+    fn fly(model: &mut Model, time: f32) {
+        for _ in 0..(time / DELTA_TIME).round() as usize {
+            model.bird_behaviour(r32(DELTA_TIME));
+            model.movement(r32(DELTA_TIME));
+        }
+    }
+
+    fn bird_state(model: &Model, bird: Id) -> (Position, vec2<Coord>) {
+        let (&position, &velocity) =
+            get!(model.birds, bird, (&body.collider.position, &body.velocity)).unwrap();
+        (position, velocity)
+    }
+
+    fn assert_close(a: vec2<Coord>, b: vec2<Coord>) {
+        assert!((a - b).len() < r32(1e-3), "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn diver_dives_at_a_visible_doodle() {
+        let (mut model, bird) = bird_model(BirdKind::Diver, vec2(1.0, 3.0), 3.0);
+        let (start, _) = bird_state(&model, bird);
+        model.bird_behaviour(r32(DELTA_TIME));
+
+        let (&diving,) = get!(model.birds, bird, (&diving)).unwrap();
+        assert!(diving);
+        let (_, velocity) = bird_state(&model, bird);
+        let doodle = model.players[0].body.unwrap();
+        let (&target,) = get!(model.doodles, doodle, (&body.collider.position)).unwrap();
+        let dive_speed = model.config.bird.dive_speed;
+        assert_close(velocity, start.delta_to(target).normalize() * dive_speed);
+
+        // Keeps the direction once diving
+        fly(&mut model, 0.1);
+        assert_close(bird_state(&model, bird).1, velocity);
+    }
+
+    #[test]
+    fn diver_does_not_dive_through_clouds() {
+        let (mut model, bird) = bird_model(BirdKind::Diver, vec2(1.0, 3.0), 3.0);
+        let cloud = Position::from_world(vec2(0.5, 1.5).as_r32(), model.world_width);
+        model.clouds.insert(Cloud::new(cloud));
+        model.update_query_grid();
+        model.bird_behaviour(r32(DELTA_TIME));
+
+        let (&diving,) = get!(model.birds, bird, (&diving)).unwrap();
+        assert!(!diving);
+        assert_close(bird_state(&model, bird).1, vec2(3.0, 0.0).as_r32());
+    }
+
+    #[test]
+    fn diver_ignores_distant_doodles() {
+        let (mut model, bird) = bird_model(BirdKind::Diver, vec2(5.0, 3.0), 3.0);
+        fly(&mut model, 0.1);
+
+        let (&diving,) = get!(model.birds, bird, (&diving)).unwrap();
+        assert!(!diving);
+    }
+
+    #[test]
+    fn sine_wave_bird_oscillates() {
+        let (mut model, bird) = bird_model(BirdKind::SineWave, vec2(0.0, 10.0), 2.0);
+        let config = model.config.bird.clone();
+        let (start, _) = bird_state(&model, bird);
+
+        let period = 2.0 * std::f32::consts::PI / config.sine_frequency.as_f32();
+        let ticks = (period / DELTA_TIME).floor() as usize;
+        let mut highest = Coord::ZERO;
+        for tick in 1..=ticks {
+            fly(&mut model, DELTA_TIME);
+            let (position, velocity) = bird_state(&model, bird);
+            let age = r32(tick as f32 * DELTA_TIME);
+            let expected =
+                config.sine_amplitude * config.sine_frequency * (config.sine_frequency * age).cos();
+            assert!((velocity.y - expected).abs() < r32(1e-3));
+            assert_eq!(velocity.x, r32(2.0));
+            highest = highest.max(start.delta_to(position).y);
+        }
+
+        assert!((highest - config.sine_amplitude).abs() < r32(0.05));
+        // Back to the starting height after a full period
+        let (position, _) = bird_state(&model, bird);
+        assert!(start.delta_to(position).y.abs() < r32(0.05));
+    }
+
+    #[test]
+    fn homing_bird_accelerates_towards_the_doodle() {
+        let (mut model, bird) = bird_model(BirdKind::Homing, vec2(8.0, 0.0), 0.0);
+        let config = model.config.bird.clone();
+
+        fly(&mut model, 0.5);
+        let speed = config.homing_acceleration * r32(0.5);
+        assert_close(bird_state(&model, bird).1, vec2(-speed, Coord::ZERO));
+
+        // Does not go faster than the homing speed
+        fly(&mut model, 1.5);
+        assert_close(
+            bird_state(&model, bird).1,
+            vec2(-config.homing_speed, Coord::ZERO),
+        );
+    }
+
+    #[test]
+    fn homing_bird_follows_across_the_seam() {
+        let (mut model, bird) = bird_model(BirdKind::Homing, vec2(0.0, 0.0), 0.0);
+        let width = model.world_width;
+        let edge = width / r32(2.0) - r32(0.5);
+        let (position,) = get!(model.birds, bird, (&mut body.collider.position)).unwrap();
+        *position = Position::from_world(vec2(edge, Coord::ZERO), width);
+        let doodle = model.players[0].body.unwrap();
+        let (position,) = get!(model.doodles, doodle, (&mut body.collider.position)).unwrap();
+        *position = Position::from_world(vec2(-edge, Coord::ZERO), width);
+
+        fly(&mut model, 0.1);
+        assert!(bird_state(&model, bird).1.x > Coord::ZERO);
+    }
+
+    #[test]
+    fn armoured_bird_flies_straight_and_slow() {
+        let (mut model, bird) = bird_model(BirdKind::Armoured, vec2(0.0, 5.0), 4.0);
+        let (start, velocity) = bird_state(&model, bird);
+        let speed = r32(4.0) * model.config.bird.armoured_speed;
+        assert_eq!(velocity, vec2(speed, Coord::ZERO));
+
+        fly(&mut model, 1.0);
+        let (position, velocity) = bird_state(&model, bird);
+        assert_eq!(velocity, vec2(speed, Coord::ZERO));
+        assert_close(start.delta_to(position), vec2(speed, Coord::ZERO));
+    }
+
+    #[test]
+    fn armoured_bird_takes_several_hits() {
+        let (mut model, bird) = bird_model(BirdKind::Armoured, vec2(5.0, 5.0), 0.0);
+        let health = model.config.bird.armoured_health;
+        for hit in 1..=health {
+            let (position, _) = bird_state(&model, bird);
+            let mut projectile = Projectile::new(
+                Body::new(Collider::new(position, Shape::circle(0.2)), 1.0),
+                1.0,
+                0,
+            );
+            projectile.body.velocity = vec2(0.0, 10.0).as_r32();
+            model.projectiles.insert(projectile);
+            model.collide_birds(r32(DELTA_TIME));
+
+            let events = model.take_events();
+            assert_eq!(events.len(), 1);
+            if hit < health {
+                assert!(matches!(events[0], Event::BirdWounded { player: 0, .. }));
+                let (&left,) = get!(model.birds, bird, (&health)).unwrap();
+                assert_eq!(left, health - hit);
+            } else {
+                assert!(matches!(events[0], Event::BirdKilled { player: 0, .. }));
+                assert!(model.birds.ids().is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn bird_kind_depends_on_height() {
+        let model = test_model(0);
+        let kinds = &model.config.generation.bird_kinds;
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut picks = |height: f32| -> Vec<BirdKind> {
+            let weights = kinds.get(r32(height));
+            (0..1000).map(|_| weights.pick(&mut rng)).collect()
+        };
+
+        assert!(picks(30.0).iter().all(|&kind| kind == BirdKind::Straight));
+
+        let low = picks(50.0);
+        assert!(low.contains(&BirdKind::Diver));
+        assert!(low.contains(&BirdKind::SineWave));
+        assert!(!low.contains(&BirdKind::Homing));
+        assert!(!low.contains(&BirdKind::Armoured));
+
+        let high = picks(400.0);
+        for kind in [
+            BirdKind::Straight,
+            BirdKind::Diver,
+            BirdKind::SineWave,
+            BirdKind::Homing,
+            BirdKind::Armoured,
+        ] {
+            assert!(high.contains(&kind), "no {kind:?} at 400");
+        }
+    }
+}
@@ -2,10 +2,16 @@ use super::*;
 
 impl Model {
     pub fn collide_clouds(&mut self, delta_time: Time) {
-        let mut particles = Vec::new();
         let mut target_shhh_volume = 0.0_f64;
+        let cloud_ids = self.clouds.ids();
+        let mut grid = Broadphase::build(
+            self.world_width,
+            cloud_ids
+                .iter()
+                .map(|&id| get!(self.clouds, id, (&body.collider)).unwrap().0),
+        );
         for body_id in self.doodles.ids() {
-            let (&body_mass, body_collider, body_vel, body_grounded, coyote_timer) = get!(
+            let (&body_mass, body_collider, body_vel, body_grounded, coyote_time) = get!(
                 self.doodles,
                 body_id,
                 (
@@ -13,16 +19,17 @@ impl Model {
                     &mut body.collider,
                     &mut body.velocity,
                     &mut grounded,
-                    &mut coyote_timer,
+                    &mut coyote_time,
                 )
             )
             .unwrap();
-            let body_col = body_collider.clone();
-            if coyote_timer.elapsed().as_secs_f64() > 0.2 {
+            let mut body_col = body_collider.clone();
+            if *coyote_time <= Time::ZERO {
                 *body_grounded = None;
             }
 
-            for cloud_id in self.clouds.ids() {
+            for index in grid.query(&body_col) {
+                let cloud_id = cloud_ids[index];
                 let (&cloud_mass, cloud_collider, cloud_vel) = get!(
                     self.clouds,
                     cloud_id,
@@ -30,70 +37,87 @@ impl Model {
                 )
                 .unwrap();
 
-                if let Some(collision) = body_col.collide(&cloud_collider.clone()) {
-                    let relative_vel = *body_vel - *cloud_vel;
-                    // Collide only when moving down
-                    if relative_vel.y > Coord::ZERO || collision.normal.y > Coord::ZERO {
-                        continue;
-                    }
-
-                    if body_grounded.is_none() {
-                        particles.push((
-                            r32(5.0),
-                            collision.point,
-                            -vec2::UNIT_Y * r32(0.1),
-                            Color::try_from("#5772B5").unwrap(),
-                        ));
-                    }
+                let cloud_col = cloud_collider.clone();
+                let (collision, impact_time) = match body_col.collide(&cloud_col) {
+                    Some(collision) => (collision, None),
+                    // Too fast, might have passed through the cloud during the tick
+                    None => match body_col.sweep(&cloud_col) {
+                        Some(impact) => (impact.collision, Some(impact.time)),
+                        None => continue,
+                    },
+                };
+                let relative_vel = *body_vel - *cloud_vel;
+                // Collide only when moving down
+                if relative_vel.y > Coord::ZERO || collision.normal.y > Coord::ZERO {
+                    continue;
+                }
 
-                    *body_grounded = Some(cloud_id);
-                    *coyote_timer = Instant::now();
-
-                    target_shhh_volume = target_shhh_volume
-                        .max((relative_vel.y.abs().as_f32() as f64 / 5.0).clamp(0.3, 1.0));
-                    if self.shhh.is_none() {
-                        self.shhh = Some(self.assets.sfx.shhh.effect());
-                        let sfx = self.shhh.as_mut().unwrap();
-                        self.shhh_volume = target_shhh_volume;
-                        sfx.set_volume(self.shhh_volume);
-                        sfx.play();
-                    }
+                if let Some(time) = impact_time {
+                    // Go back to the point of landing
+                    body_collider.position = body_col.position_at(time);
+                    body_col = body_collider.clone();
+                }
 
-                    let body_factor = cloud_mass / (body_mass + cloud_mass);
-                    let cloud_factor = body_mass / (body_mass + cloud_mass);
+                if body_grounded.is_none() {
+                    self.events.push(Event::LandedOnCloud {
+                        doodle: body_id,
+                        cloud: cloud_id,
+                        position: collision.point,
+                        speed: relative_vel.y.abs(),
+                    });
+                }
 
-                    // Move the cloud
-                    let penetration = collision.normal * collision.penetration;
-                    let cloud_shift_speed = 5.0.as_r32();
-                    cloud_collider.position.shift(
-                        vec2::UNIT_Y * penetration.y.clamp_abs(cloud_shift_speed * delta_time),
-                    );
+                *body_grounded = Some(cloud_id);
+                *coyote_time = self.config.doodle.coyote_time;
 
-                    // Fix horizontal velocity
-                    cloud_vel.y += relative_vel.y * cloud_factor;
-                    body_vel.y -= relative_vel.y * body_factor;
+                target_shhh_volume = target_shhh_volume
+                    .max((relative_vel.y.abs().as_f32() as f64 / 5.0).clamp(0.3, 1.0));
+                if !self.shhh_playing {
+                    self.shhh_playing = true;
+                    self.shhh_volume = target_shhh_volume;
                 }
-            }
-        }
 
-        for (intensity, position, velocity, color) in particles {
-            self.spawn_particles(intensity, position, velocity, color);
+                let body_factor = cloud_mass / (body_mass + cloud_mass);
+                let cloud_factor = body_mass / (body_mass + cloud_mass);
+
+                // Move the cloud
+                let penetration = collision.normal * collision.penetration;
+                let push_speed = self.config.cloud.push_speed;
+                cloud_collider
+                    .position
+                    .shift(vec2::UNIT_Y * penetration.y.clamp_abs(push_speed * delta_time));
+                // Keep the grid up to date for the other doodles
+                grid.insert(index, cloud_collider);
+
+                // Fix horizontal velocity
+                cloud_vel.y += relative_vel.y * cloud_factor;
+                body_vel.y -= relative_vel.y * body_factor;
+            }
         }
 
         let fade_time = 0.3;
         self.shhh_volume += (target_shhh_volume - self.shhh_volume)
             .clamp_abs(delta_time.as_f32() as f64 / fade_time);
-        if let Some(sfx) = &mut self.shhh {
-            sfx.set_volume(self.shhh_volume);
-            if self.shhh_volume <= 1e-5 {
-                sfx.stop();
-                self.shhh = None;
-            }
+        if self.shhh_playing && self.shhh_volume <= 1e-5 {
+            self.shhh_playing = false;
         }
     }
 
     pub fn collide_birds(&mut self, _delta_time: Time) {
-        let mut particles = Vec::new();
+        let doodle_ids = self.doodles.ids();
+        let doodle_grid = Broadphase::build(
+            self.world_width,
+            doodle_ids
+                .iter()
+                .map(|&id| get!(self.doodles, id, (&body.collider)).unwrap().0),
+        );
+        let projectile_ids = self.projectiles.ids();
+        let projectile_grid = Broadphase::build(
+            self.world_width,
+            projectile_ids
+                .iter()
+                .map(|&id| get!(self.projectiles, id, (&body.collider)).unwrap().0),
+        );
 
         'bird: for bird_id in self.birds.ids() {
             let (&bird_mass, bird_collider, &bird_vel) = get!(
@@ -104,78 +128,119 @@ impl Model {
             .unwrap();
             let bird_col = bird_collider.clone();
 
-            for body_id in self.doodles.ids() {
-                let (&body_mass, body_collider, body_vel) = get!(
+            for index in doodle_grid.query(&bird_col) {
+                let body_id = doodle_ids[index];
+                let (&body_mass, body_collider, body_vel, power_ups) = get!(
                     self.doodles,
                     body_id,
-                    (&body.mass, &mut body.collider, &mut body.velocity)
+                    (
+                        &body.mass,
+                        &mut body.collider,
+                        &mut body.velocity,
+                        &mut power_ups
+                    )
                 )
                 .unwrap();
                 let body_col = body_collider.clone();
 
-                if let Some(_collision) = body_col.collide(&bird_col) {
+                let hit = body_col
+                    .collide(&bird_col)
+                    .or_else(|| body_col.sweep(&bird_col).map(|impact| impact.collision));
+                if let Some(_collision) = hit {
+                    self.birds.remove(bird_id);
+                    if power_ups.remove(PowerUp::Shield) {
+                        self.events.push(Event::ShieldBroken {
+                            doodle: body_id,
+                            position: bird_col.position,
+                        });
+                        continue 'bird;
+                    }
+
                     let body_factor = bird_mass / body_mass;
                     *body_vel += bird_vel * body_factor;
                     *body_vel -= vec2::UNIT_Y * body_vel.y * r32(0.5);
-                    self.birds.remove(bird_id);
-                    self.assets.sfx.oi.play();
-                    self.score -= 50;
-                    particles.push((
-                        r32(5.0),
-                        *body_collider.position,
-                        bird_vel * r32(0.3),
-                        Color::try_from("#B16B7E").unwrap(),
-                    ));
+                    self.events.push(Event::BirdHitPlayer {
+                        doodle: body_id,
+                        position: *body_collider.position,
+                        bird_velocity: bird_vel,
+                    });
                     continue 'bird;
                 }
             }
 
-            for proj_id in self.projectiles.ids() {
-                let (proj_collider, &proj_vel) =
-                    get!(self.projectiles, proj_id, (&body.collider, &body.velocity)).unwrap();
+            for index in projectile_grid.query(&bird_col) {
+                let proj_id = projectile_ids[index];
+                // Might have already hit another bird
+                let Some((proj_collider, &proj_vel, &owner)) = get!(
+                    self.projectiles,
+                    proj_id,
+                    (&body.collider, &body.velocity, &owner)
+                ) else {
+                    continue;
+                };
                 let proj_col = proj_collider.clone();
 
-                if let Some(_collision) = bird_col.collide(&proj_col) {
+                let hit = bird_col
+                    .collide(&proj_col)
+                    .or_else(|| bird_col.sweep(&proj_col).map(|impact| impact.collision));
+                if let Some(_collision) = hit {
                     self.projectiles.remove(proj_id);
+                    let (health,) = get!(self.birds, bird_id, (&mut health)).unwrap();
+                    *health = health.saturating_sub(1);
+                    if *health > 0 {
+                        // Armoured birds take several hits
+                        self.events.push(Event::BirdWounded {
+                            player: owner,
+                            position: bird_col.position,
+                            projectile_velocity: proj_vel,
+                        });
+                        continue;
+                    }
+
                     self.birds.remove(bird_id);
-                    self.assets.sfx.kill_bird.play();
-                    self.score += 100;
-                    particles.push((
-                        r32(3.0),
-                        bird_col.position,
-                        proj_vel * r32(0.3),
-                        Color::try_from("#4B071A").unwrap(),
-                    ));
+                    self.events.push(Event::BirdKilled {
+                        player: owner,
+                        position: bird_col.position,
+                        projectile_velocity: proj_vel,
+                    });
                     continue 'bird;
                 }
             }
         }
-
-        for (intensity, position, velocity, color) in particles {
-            self.spawn_particles(intensity, position, velocity, color);
-        }
     }
 
     pub fn collide_triggers(&mut self, _delta_time: Time) {
-        let mut particles = Vec::new();
+        let trigger_ids = self.triggers.ids();
+        let grid = Broadphase::build(
+            self.world_width,
+            trigger_ids
+                .iter()
+                .map(|&id| get!(self.triggers, id, (&collider)).unwrap().0),
+        );
         for body_id in self.doodles.ids() {
-            let (body_collider, body_vel, &body_mass, active_triggers) = get!(
+            let (body_collider, body_vel, &body_mass, active_triggers, power_ups) = get!(
                 self.doodles,
                 body_id,
                 (
                     &body.collider,
                     &mut body.velocity,
                     &body.mass,
-                    &mut active_triggers
+                    &mut active_triggers,
+                    &mut power_ups
                 )
             )
             .unwrap();
             let body_col = body_collider.clone();
 
             let mut triggers = Vec::new();
-            for trigger_id in self.triggers.ids() {
-                let (trigger_kind, trigger_collider, attachment) =
-                    get!(self.triggers, trigger_id, (&kind, &collider, &attached_to)).unwrap();
+            for index in grid.query(&body_col) {
+                let trigger_id = trigger_ids[index];
+                // Might have already been collected by another doodle
+                let Some((trigger_kind, trigger_collider, attachment)) =
+                    get!(self.triggers, trigger_id, (&kind, &collider, &attached_to))
+                else {
+                    continue;
+                };
                 let trigger_col = trigger_collider.clone();
 
                 if let Some(_collision) = body_col.collide(&trigger_col) {
@@ -186,16 +251,18 @@ impl Model {
 
                     match trigger_kind {
                         TriggerKind::Spring => {
-                            let jump = 10.0.as_r32();
                             let dir = vec2::UNIT_Y;
-                            let jump = dir * jump;
+                            let jump = dir * self.config.doodle.spring_jump;
                             *body_vel += jump;
 
                             // Make sure we jump up with a minimum speed
-                            let min_jump_speed = 15.0.as_r32();
+                            let min_jump_speed = self.config.doodle.spring_min_speed;
                             let proj = vec2::dot(*body_vel, dir);
                             *body_vel += dir * (min_jump_speed - proj).max(R32::ZERO);
-                            self.assets.sfx.spring.play();
+                            self.events.push(Event::SpringBounced {
+                                doodle: body_id,
+                                position: trigger_col.position,
+                            });
 
                             if let Some(attachment) = attachment {
                                 if let Some((cloud_velocity, &cloud_mass)) = get!(
@@ -207,35 +274,174 @@ impl Model {
                                     *cloud_velocity -= jump * r32(0.3) * cloud_factor;
                                 }
                             }
-
-                            particles.push((
-                                r32(5.0),
-                                trigger_col.position,
-                                -vec2::UNIT_Y * r32(0.2),
-                                Color::try_from("#2148AB").unwrap(),
-                            ));
                         }
                         TriggerKind::Coin => {
                             self.triggers.remove(trigger_id);
-                            self.score += 100;
-                            let mut sfx = self.assets.sfx.coin.effect();
-                            sfx.set_volume(0.2);
-                            sfx.play();
-                            particles.push((
-                                r32(5.0),
-                                trigger_col.position,
-                                vec2::ZERO,
-                                Color::try_from("#E6AC4C").unwrap(),
-                            ));
+                            self.events.push(Event::CoinCollected {
+                                doodle: body_id,
+                                position: trigger_col.position,
+                            });
+                        }
+                        &TriggerKind::PowerUp(kind) => {
+                            power_ups.activate(kind, self.config.power_up.duration(kind));
+                            self.triggers.remove(trigger_id);
+                            self.events.push(Event::PowerUpCollected {
+                                doodle: body_id,
+                                kind,
+                                position: trigger_col.position,
+                            });
                         }
                     }
                 }
             }
             *active_triggers = triggers;
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A model with a single cloud at the origin and the doodle at `offset` from it.
+    fn doodle_over_cloud(offset: vec2<f32>, velocity: vec2<f32>) -> (Model, Id, Id) {
+        let mut model = test_model(0);
+        let width = model.world_width;
+        let cloud = model.clouds.insert(Cloud::new(Position::zero(width)));
+        let doodle = model.players[0].body.unwrap();
+        let (collider, body_velocity) = get!(
+            model.doodles,
+            doodle,
+            (&mut body.collider, &mut body.velocity)
+        )
+        .unwrap();
+        *collider = Collider::new(
+            Position::from_world(offset.as_r32(), width),
+            collider.shape().clone(),
+        );
+        *body_velocity = velocity.as_r32();
+        (model, doodle, cloud)
+    }
+
+    /// Like `doodle_over_cloud`, but the cloud has the given shape.
+    fn doodle_over_shape(shape: Shape, offset: vec2<f32>, velocity: vec2<f32>) -> (Model, Id, Id) {
+        let (mut model, doodle, cloud) = doodle_over_cloud(offset, velocity);
+        let (collider,) = get!(model.clouds, cloud, (&mut body.collider)).unwrap();
+        collider.set_shape(shape);
+        (model, doodle, cloud)
+    }
+
+    /// Move the bodies and collide them with the clouds, without gravity or input.
+    fn step(model: &mut Model, ticks: usize) {
+        let delta_time = r32(1.0 / 120.0);
+        for _ in 0..ticks {
+            model.remember_positions();
+            model.movement(delta_time);
+            model.collide_clouds(delta_time);
+        }
+    }
+
+    /// Vertical offset of the doodle from the cloud.
+    fn height_above(model: &Model, doodle: Id, cloud: Id) -> Coord {
+        let (&doodle_pos,) = get!(model.doodles, doodle, (&body.collider.position)).unwrap();
+        let (&cloud_pos,) = get!(model.clouds, cloud, (&body.collider.position)).unwrap();
+        cloud_pos.delta_to(doodle_pos).y
+    }
+
+    #[test]
+    fn falling_doodle_lands_on_cloud() {
+        let (mut model, doodle, cloud) = doodle_over_cloud(vec2(0.0, 0.6), vec2(0.0, -3.0));
+
+        model.collide_clouds(r32(1.0 / 120.0));
+
+        let (&grounded, &velocity) =
+            get!(model.doodles, doodle, (&grounded, &body.velocity)).unwrap();
+        assert_eq!(grounded, Some(cloud));
+        assert!(velocity.y > r32(-3.0));
+        assert!(model.take_events().iter().any(
+            |event| matches!(event, Event::LandedOnCloud { cloud: landed, .. } if *landed == cloud)
+        ));
+    }
+
+    #[test]
+    fn fast_doodle_does_not_fall_through_cloud() {
+        // Two units in a single tick, more than the doodle and the cloud together
+        let (mut model, doodle, cloud) = doodle_over_cloud(vec2(0.0, 1.0), vec2(0.0, -40.0));
+        let delta_time = r32(0.05);
+        model.movement(delta_time);
+        model.collide_clouds(delta_time);
+
+        let (&grounded, &position) =
+            get!(model.doodles, doodle, (&grounded, &body.collider.position)).unwrap();
+        assert_eq!(grounded, Some(cloud));
+        assert!(position.to_world().y > r32(0.0));
+    }
 
-        for (intensity, position, velocity, color) in particles {
-            self.spawn_particles(intensity, position, velocity, color);
+    #[test]
+    fn rising_doodle_passes_through_cloud() {
+        let (mut model, doodle, _) = doodle_over_cloud(vec2(0.0, 0.6), vec2(0.0, 3.0));
+
+        model.collide_clouds(r32(1.0 / 120.0));
+
+        let (&grounded,) = get!(model.doodles, doodle, (&grounded)).unwrap();
+        assert_eq!(grounded, None);
+    }
+
+    #[test]
+    fn capsule_doodle_lands_on_cloud() {
+        let (mut model, doodle, cloud) = doodle_over_cloud(vec2(0.0, 0.8), vec2(0.0, -3.0));
+        let (collider,) = get!(model.doodles, doodle, (&body.collider)).unwrap();
+        assert!(matches!(collider.shape(), Shape::Capsule { .. }));
+
+        step(&mut model, 10);
+
+        let (&grounded,) = get!(model.doodles, doodle, (&grounded)).unwrap();
+        assert_eq!(grounded, Some(cloud));
+        assert!(height_above(&model, doodle, cloud) > r32(0.5));
+    }
+
+    #[test]
+    fn doodle_lands_on_polygon_slope() {
+        let slope = Shape::convex_polygon(
+            [(-1.0, 0.0), (1.0, 0.5), (-1.0, -0.25), (1.0, -0.25)]
+                .into_iter()
+                .map(|(x, y)| vec2(x, y).as_r32()),
+        );
+        let (mut model, doodle, cloud) = doodle_over_shape(slope, vec2(0.5, 1.2), vec2(0.0, -3.0));
+
+        step(&mut model, 30);
+
+        let (&grounded,) = get!(model.doodles, doodle, (&grounded)).unwrap();
+        assert_eq!(grounded, Some(cloud));
+        // The slope is 0.375 high under the doodle
+        assert!(height_above(&model, doodle, cloud) > r32(0.3));
+    }
+
+    #[test]
+    fn one_way_segment_is_solid_only_from_above() {
+        let platform = Shape::Segment {
+            a: vec2(-1.0, 0.0).as_r32(),
+            b: vec2(1.0, 0.0).as_r32(),
+            one_way: true,
+        };
+        let (mut model, doodle, cloud) =
+            doodle_over_shape(platform, vec2(0.0, -1.0), vec2(0.0, 6.0));
+
+        // Jump up through it
+        for _ in 0..40 {
+            step(&mut model, 1);
+            let (&grounded,) = get!(model.doodles, doodle, (&grounded)).unwrap();
+            assert_eq!(grounded, None);
         }
+        assert!(height_above(&model, doodle, cloud) > r32(0.5));
+
+        // And land on it
+        let (velocity,) = get!(model.doodles, doodle, (&mut body.velocity)).unwrap();
+        *velocity = vec2(0.0, -3.0).as_r32();
+        step(&mut model, 40);
+
+        let (&grounded,) = get!(model.doodles, doodle, (&grounded)).unwrap();
+        assert_eq!(grounded, Some(cloud));
+        assert!(height_above(&model, doodle, cloud) > r32(0.4));
     }
 }
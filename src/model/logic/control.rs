@@ -1,21 +1,26 @@
 use super::*;
 
 impl Model {
-    pub fn player_control(&mut self, input: PlayerInput, delta_time: Time) {
-        let (&position, velocity, &grounded, shoot_timer, &mass) = get!(
+    pub fn player_control(&mut self, player: usize, input: &PlayerInput, delta_time: Time) {
+        let Some(doodle) = self.players[player].body else {
+            return;
+        };
+        let (&position, velocity, &grounded, shoot_cooldown, &mass, power_ups) = get!(
             self.doodles,
-            self.player.body,
+            doodle,
             (
                 &body.collider.position,
                 &mut body.velocity,
                 &grounded,
-                &mut shoot_timer,
-                &body.mass
+                &mut shoot_cooldown,
+                &body.mass,
+                &power_ups
             )
         )
         .unwrap();
-        let speed = 5.0.as_r32();
-        let acceleration = 50.0.as_r32();
+        let config = &self.config.doodle;
+        let speed = config.move_speed;
+        let acceleration = config.acceleration;
         let ground_vel = match grounded {
             Some(cloud) => {
                 let (&vel, &anchor_vel) =
@@ -30,8 +35,7 @@ impl Model {
 
         if input.jump {
             if let Some(cloud) = grounded {
-                let jump = 5.0.as_r32();
-                let jump = vec2::UNIT_Y * jump;
+                let jump = vec2::UNIT_Y * config.jump;
                 *velocity += jump;
 
                 // Push off the cloud
@@ -40,48 +44,66 @@ impl Model {
                 let cloud_factor = mass / (mass + cloud_mass);
                 *cloud_vel -= jump * cloud_factor;
 
-                self.assets.sfx.jump.play();
+                self.events.push(Event::Jumped { doodle, position });
             }
         }
 
-        if input.shoot && shoot_timer.elapsed().as_secs_f64() > 0.5 {
-            self.assets.sfx.shoot.play();
-            *shoot_timer = Instant::now();
-            let target_pos = self.camera.cursor_pos_world();
-            let delta = position.delta_to(target_pos);
-            let dir = delta.normalize_or_zero();
+        if input.shoot && *shoot_cooldown <= Time::ZERO {
+            *shoot_cooldown = if power_ups.is_active(PowerUp::RapidFire) {
+                self.config.power_up.rapid_fire_cooldown
+            } else {
+                config.shoot_cooldown
+            };
+            let dir = match input.aim {
+                Aim::Position(aim) => position.delta_to(aim),
+                Aim::Direction(dir) => dir,
+            }
+            .normalize_or_zero();
 
-            let speed = 10.0.as_r32();
+            let speed = config.projectile_speed;
             let mut proj = Projectile::new(
                 Body::new(Collider::new(position, Shape::circle(0.2)), 1.0),
-                1.0,
+                config.projectile_lifetime,
+                player,
             );
             proj.body.velocity = dir * speed;
             self.projectiles.insert(proj);
 
             // Recoil
-            let recoil = 5.0.as_r32();
-            *velocity -= dir * recoil;
+            *velocity -= dir * config.recoil;
 
-            self.spawn_particles(
-                r32(3.0),
+            self.events.push(Event::ShotFired {
+                doodle,
                 position,
-                dir * speed * r32(0.3),
-                Rgba::try_from("#4B071A").unwrap(),
-            );
+                velocity: dir * speed,
+            });
         }
     }
 
+    /// The camera rises with the highest player,
+    /// and horizontally stays in the middle of all the players.
     pub fn camera_control(&mut self, delta_time: Time) {
-        let (&player_pos, &_player_vel) = get!(
-            self.doodles,
-            self.player.body,
-            (&body.collider.position, &body.velocity)
-        )
-        .unwrap();
+        let Some(leader) = self.leader() else {
+            return;
+        };
+        let (&leader_pos,) = get!(self.doodles, leader, (&body.collider.position)).unwrap();
+        let bodies: Vec<Id> = self
+            .players
+            .iter()
+            .filter_map(|player| player.body)
+            .collect();
+        let spread = bodies
+            .iter()
+            .map(|&id| {
+                let (&pos,) = get!(self.doodles, id, (&body.collider.position)).unwrap();
+                leader_pos.delta_to(pos).x
+            })
+            .fold(Coord::ZERO, |acc, x| acc + x)
+            / r32(bodies.len() as f32);
+        let target = leader_pos.shifted(vec2(spread, Coord::ZERO));
 
         self.camera.target_position.shift({
-            let mut delta = self.camera.target_position.delta_to(player_pos);
+            let mut delta = self.camera.target_position.delta_to(target);
             delta.y = delta.y.max(R32::ZERO);
             delta
         });
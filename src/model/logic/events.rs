@@ -0,0 +1,183 @@
+use super::*;
+
+impl Model {
+    /// React to the events produced during this tick.
+    pub fn process_events(&mut self) {
+        let events = self.events.clone();
+        for event in events {
+            self.event_score(&event);
+            self.event_stats(&event);
+            self.event_particles(&event);
+        }
+    }
+
+    fn event_score(&mut self, event: &Event) {
+        let (player, points) = match *event {
+            Event::CoinCollected { doodle, .. } => (self.player_of(doodle), 100),
+            Event::BirdHitPlayer { doodle, .. } => (self.player_of(doodle), -50),
+            Event::BirdKilled { player, .. } => (Some(player), 100),
+            _ => return,
+        };
+        if let Some(player) = player {
+            self.players[player].score += points;
+        }
+    }
+
+    fn event_stats(&mut self, event: &Event) {
+        if let Event::CoinCollected { doodle, .. } = *event {
+            if let Some(player) = self.player_of(doodle) {
+                self.players[player].stats.coins_collected += 1;
+            }
+        }
+    }
+
+    fn event_particles(&mut self, event: &Event) {
+        let (intensity, position, velocity, color) = match *event {
+            Event::LandedOnCloud { position, .. } => {
+                (5.0, position, -vec2::UNIT_Y * r32(0.1), "#5772B5")
+            }
+            Event::SpringBounced { position, .. } => {
+                (5.0, position, -vec2::UNIT_Y * r32(0.2), "#2148AB")
+            }
+            Event::CoinCollected { position, .. } => (5.0, position, vec2::ZERO, "#E6AC4C"),
+            Event::PowerUpCollected { kind, position, .. } => {
+                self.spawn_particles(r32(10.0), position, vec2::UNIT_Y * r32(0.5), kind.color());
+                return;
+            }
+            Event::ShieldBroken { position, .. } => {
+                self.spawn_particles(r32(8.0), position, vec2::ZERO, PowerUp::Shield.color());
+                return;
+            }
+            Event::BirdHitPlayer {
+                position,
+                bird_velocity,
+                ..
+            } => (5.0, position, bird_velocity * r32(0.3), "#B16B7E"),
+            Event::BirdWounded {
+                position,
+                projectile_velocity,
+                ..
+            } => (3.0, position, projectile_velocity * r32(0.3), "#8C8C99"),
+            Event::BirdKilled {
+                position,
+                projectile_velocity,
+                ..
+            } => (3.0, position, projectile_velocity * r32(0.3), "#4B071A"),
+            Event::ShotFired {
+                position, velocity, ..
+            } => (3.0, position, velocity * r32(0.3), "#4B071A"),
+            Event::Jumped { .. } | Event::Died { .. } => return,
+        };
+        self.spawn_particles(
+            r32(intensity),
+            position,
+            velocity,
+            Color::try_from(color).unwrap(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(dir_x: Coord, jump: bool, shoot: bool) -> PlayerInput {
+        PlayerInput {
+            input_dir: vec2(dir_x, Coord::ZERO),
+            jump,
+            shoot,
+            aim: Aim::Direction(vec2::UNIT_X),
+        }
+    }
+
+    #[test]
+    fn jump_shoot_and_die() {
+        let mut model = test_model(0);
+        // Nothing else gets generated
+        model.generated_height = r32(1e6);
+        let width = model.world_width;
+        let at = |x: f32, y: f32| Position::from_world(vec2(x, y).as_r32(), width);
+        let start = model.clouds.insert(Cloud::new(at(0.0, 0.0)));
+        let target = model.clouds.insert(Cloud::new(at(2.0, 1.0)));
+        model.birds.insert(Bird::new(at(6.0, 1.75), 0.0));
+
+        // Stand on the starting cloud
+        let doodle = model.players[0].body.unwrap();
+        let (collider, grounded) =
+            get!(model.doodles, doodle, (&mut body.collider, &mut grounded)).unwrap();
+        *collider = Collider::new(at(0.0, 0.75), collider.shape().clone());
+        *grounded = Some(start);
+
+        let delta_time = r32(1.0 / 120.0);
+        let mut events = Vec::new();
+        let mut tick = |model: &mut Model, input: PlayerInput| {
+            model.update(&[input], delta_time);
+            // Only the events of this tick are left
+            events.extend(model.events.iter().cloned());
+        };
+        let steer = |model: &Model| {
+            let (&position,) = get!(model.doodles, doodle, (&body.collider.position)).unwrap();
+            let (&target_pos,) = get!(model.clouds, target, (&body.collider.position)).unwrap();
+            (position.delta_to(target_pos).x / r32(0.5)).clamp_abs(Coord::ONE)
+        };
+        let grounded_on = |model: &Model| *get!(model.doodles, doodle, (&grounded)).unwrap().0;
+
+        // Jump onto the cloud to the right
+        let dir = steer(&model);
+        tick(&mut model, input(dir, true, false));
+        for _ in 0..240 {
+            if grounded_on(&model) == Some(target) {
+                break;
+            }
+            let dir = steer(&model);
+            tick(&mut model, input(dir, false, false));
+        }
+        assert_eq!(grounded_on(&model), Some(target));
+        for _ in 0..30 {
+            tick(&mut model, input(Coord::ZERO, false, false));
+        }
+
+        // Shoot the bird to the right
+        tick(&mut model, input(Coord::ZERO, false, true));
+        for _ in 0..120 {
+            if model.birds.ids().is_empty() {
+                break;
+            }
+            tick(&mut model, input(Coord::ZERO, false, false));
+        }
+        assert!(model.birds.ids().is_empty());
+        assert_eq!(model.players[0].score, 100);
+
+        // Walk off the cloud and fall off the screen
+        for _ in 0..600 {
+            if model.players[0].body.is_none() {
+                break;
+            }
+            tick(&mut model, input(Coord::ONE, false, false));
+        }
+        assert!(model.is_game_over());
+
+        let sequence: Vec<&str> = events
+            .iter()
+            .map(|event| match *event {
+                Event::Jumped { doodle: id, .. } if id == doodle => "jumped",
+                Event::LandedOnCloud {
+                    doodle: id, cloud, ..
+                } if id == doodle && cloud == target => "landed",
+                Event::ShotFired { doodle: id, .. } if id == doodle => "shot",
+                Event::BirdKilled { player: 0, .. } => "killed",
+                Event::Died { doodle: id, .. } if id == doodle => "died",
+                _ => "unexpected",
+            })
+            .collect();
+        assert_eq!(
+            sequence,
+            ["jumped", "landed", "shot", "killed", "died"],
+            "{events:#?}"
+        );
+
+        // The death is not reported again
+        model.update(&[], delta_time);
+        assert!(model.events.is_empty());
+    }
+}
@@ -1,14 +1,22 @@
 use super::*;
 
+use std::ops::RangeInclusive;
+
 impl Model {
     pub fn generate_level(&mut self, delta_time: Time) {
-        let mut rng = thread_rng();
-        let (&player_pos, &player_vel) = get!(
+        // Generate ahead of the highest player
+        let Some(leader) = self.leader() else {
+            return;
+        };
+        let rng = &mut self.rng;
+        let config = &self.config.generation;
+        let (&player_pos, &player_vel, player_collider) = get!(
             self.doodles,
-            self.player.body,
-            (&body.collider.position, &body.velocity)
+            leader,
+            (&body.collider.position, &body.velocity, &body.collider)
         )
         .unwrap();
+        let player_width = player_collider.clone().compute_aabb().width();
 
         if self.clouds.ids().is_empty() {
             // Initial stuff
@@ -23,8 +31,11 @@ impl Model {
                 })
                 .collect();
 
+            let top_cloud = *clouds.last().unwrap();
+            self.last_generated_cloud = Some(top_cloud);
+            let (&top_position,) = get!(self.clouds, top_cloud, (&body.collider.position)).unwrap();
             self.triggers
-                .insert(Trigger::spring(*clouds.last().unwrap(), self.world_width));
+                .insert(Trigger::spring(top_cloud, top_position));
 
             self.birds.insert(Bird::new(
                 Position::from_world(vec2(-3.0, 3.0).as_r32(), self.world_width),
@@ -32,11 +43,24 @@ impl Model {
             ));
         }
 
+        // Scripted birds
+        let player_height = player_pos.to_world().y;
+        let (ready, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.scripted_birds)
+            .into_iter()
+            .partition(|bird| player_height >= bird.spawn_height);
+        self.scripted_birds = waiting;
+        for bird in ready {
+            self.birds.insert(
+                Bird::new(bird.position, bird.speed).with_kind(bird.kind, &self.config.bird),
+            );
+        }
+
         // Birds
-        if player_pos.to_world().y > r32(30.0) {
+        if player_pos.to_world().y > config.bird_height {
+            let difficulty_height = player_pos.to_world().y;
             self.next_bird -= delta_time;
             while self.next_bird < Time::ZERO {
-                self.next_bird += rng.gen_range(0.7..=2.0).as_r32();
+                self.next_bird += gen_range(rng, &config.bird_interval.get(difficulty_height));
 
                 let predict_time = rng.gen_range(1.0..=2.0).as_r32();
                 let height = predict_time * player_vel.y;
@@ -44,44 +68,203 @@ impl Model {
                 let position = player_pos.shifted(vec2(self.world_width / r32(2.0), height));
 
                 let dir = if rng.gen() { 1.0 } else { -1.0 };
-                let speed = rng.gen_range(4.0..=6.0);
+                let speed = gen_range(rng, &config.bird_speed.get(difficulty_height));
 
-                self.birds.insert(Bird::new(position, dir * speed));
+                let kind = config.bird_kinds.get(difficulty_height).pick(rng);
+                self.birds.insert(
+                    Bird::new(position, r32(dir) * speed).with_kind(kind, &self.config.bird),
+                );
             }
         }
 
         // Clouds
-        let gen_ahead = 20.0.as_r32();
-        while player_pos.to_world().y + gen_ahead > self.generated_height {
-            let height = rng.gen_range(0.5..=2.0).as_r32();
+        while player_pos.to_world().y + self.config.generation.generate_ahead
+            > self.generated_height
+        {
+            let config = &self.config.generation;
+            let difficulty_height = self.generated_height;
+            let height = gen_range(&mut self.rng, &config.cloud_gap.get(difficulty_height));
             let y = self.generated_height + height;
             self.generated_height = y;
-            let x = rng.gen_range(0.0..=self.world_width.as_f32()).as_r32();
+            let x = self.rng.gen_range(0.0..=self.world_width.as_f32()).as_r32();
             let position = Position::from_world(vec2(x, y), self.world_width);
 
             let mut cloud = Cloud::new(position);
+            let cloud_width = cloud.body.collider.compute_aabb().width();
+            let landing_width = (player_width + cloud_width) / r32(2.0);
+
+            let chunk = self
+                .chunks
+                .iter()
+                .find(|chunk| chunk.fits(y) && gen_chance(&mut self.rng, chunk.chance))
+                .cloned();
+            if let Some(chunk) = chunk {
+                self.place_chunk(&chunk, position, player_width);
+                continue;
+            }
 
-            if rng.gen_bool(0.3) {
+            if gen_chance(
+                &mut self.rng,
+                config.moving_cloud_chance.get(difficulty_height),
+            ) {
                 // Moving cloud
-                let dir = if rng.gen() { 1.0 } else { -1.0 }.as_r32();
-                let speed = rng.gen_range(2.0..=4.0).as_r32();
+                let dir = if self.rng.gen() { 1.0 } else { -1.0 }.as_r32();
+                let speed = gen_range(
+                    &mut self.rng,
+                    &config.moving_cloud_speed.get(difficulty_height),
+                );
                 cloud.anchor_velocity = vec2::UNIT_X * dir * speed;
             }
 
+            // Make sure the cloud can be reached from the previous one
+            let (position, velocity) = self.reachable_position(
+                cloud.anchor,
+                cloud.anchor_velocity,
+                landing_width,
+                surface_offset(&cloud.body.collider),
+            );
+            cloud = Cloud {
+                anchor_velocity: velocity,
+                ..Cloud::new(position)
+            };
+            self.generated_height = position.to_world().y;
+
             let cloud = self.clouds.insert(cloud);
+            self.last_generated_cloud = Some(cloud);
 
-            if rng.gen_bool(0.1) {
+            if gen_chance(&mut self.rng, config.spring_chance.get(difficulty_height)) {
                 // With a spring
+                self.triggers.insert(Trigger::spring(cloud, position));
+            } else if gen_chance(&mut self.rng, config.power_up_chance.get(difficulty_height)) {
+                // With a power-up
+                let kind = *PowerUp::ALL.choose(&mut self.rng).unwrap();
                 self.triggers
-                    .insert(Trigger::spring(cloud, self.world_width));
-            } else if rng.gen_bool(0.75) {
+                    .insert(Trigger::power_up(kind, cloud, position));
+            } else if gen_chance(&mut self.rng, config.coin_chance.get(difficulty_height)) {
                 // With a coin
-                self.triggers
-                    .insert(Trigger::coin(Some(cloud), self.world_width));
+                self.triggers.insert(Trigger::coin(cloud, position));
             }
         }
     }
 
+    /// Returns the closest position and velocity to the given ones,
+    /// at which a cloud can be reached from the last generated cloud.
+    /// The `surface` is the height of the cloud's top above its anchor.
+    fn reachable_position(
+        &self,
+        position: Position,
+        velocity: vec2<Coord>,
+        landing_width: Coord,
+        surface: Coord,
+    ) -> (Position, vec2<Coord>) {
+        let Some((&prev_anchor, &prev_velocity, prev_collider)) =
+            self.last_generated_cloud.and_then(|prev| {
+                get!(
+                    self.clouds,
+                    prev,
+                    (&anchor, &anchor_velocity, &body.collider)
+                )
+            })
+        else {
+            return (position, velocity);
+        };
+        let arc = JumpArc::new(&self.config, self.world_width, landing_width);
+        // The doodle jumps off and lands on the surfaces, not the anchors
+        let from = prev_anchor.shifted(vec2(Coord::ZERO, surface_offset(prev_collider)));
+        let to = position.shifted(vec2(Coord::ZERO, surface));
+        let (to, velocity) = arc.repair(from, prev_velocity, to, velocity);
+        (to.shifted(vec2(Coord::ZERO, -surface)), velocity)
+    }
+
+    fn place_chunk(&mut self, chunk: &Chunk, mut origin: Position, player_width: Coord) {
+        // Place the clouds from the bottom up,
+        // so that each one can be checked against the ones below
+        let mut chunk_clouds: Vec<&ChunkCloud> = chunk.clouds.iter().collect();
+        chunk_clouds.sort_by_key(|cloud| cloud.position.y);
+
+        // Surface, velocity and whether there is a spring, for every placed cloud
+        let mut placed: Vec<(Position, vec2<Coord>, bool)> = Vec::new();
+        let mut top_cloud = None;
+        for chunk_cloud in chunk_clouds {
+            let new_cloud = |position: Position, velocity: vec2<Coord>| {
+                let mut cloud = Cloud {
+                    anchor_velocity: velocity,
+                    ..Cloud::new(position)
+                };
+                if let Some(shape) = chunk_cloud.shape() {
+                    cloud.body.collider.set_shape(shape);
+                }
+                cloud
+            };
+            let cloud = new_cloud(origin.shifted(chunk_cloud.position), chunk_cloud.velocity);
+            let cloud_width = cloud.body.collider.compute_aabb().width();
+            let landing_width = (player_width + cloud_width) / r32(2.0);
+            let arc = JumpArc::new(&self.config, self.world_width, landing_width);
+
+            let surface = vec2(Coord::ZERO, surface_offset(&cloud.body.collider));
+            let (anchor, to_velocity) = (cloud.anchor, cloud.anchor_velocity);
+            let to = anchor.shifted(surface);
+            let (position, velocity) = if placed.is_empty() {
+                // Make sure the lowest cloud of the chunk can be reached,
+                // and move the whole chunk with it
+                let (position, velocity) =
+                    self.reachable_position(anchor, to_velocity, landing_width, surface.y);
+                origin = origin.shifted(anchor.delta_to(position));
+                (position.shifted(surface), velocity)
+            } else {
+                // Make sure the chunk's own layout is possible
+                let spring_arc = arc.spring(&self.config);
+                let arc_from = |spring: bool| if spring { spring_arc } else { arc };
+                let reachable = placed.iter().any(|&(from, from_velocity, spring)| {
+                    arc_from(spring).can_reach(from, from_velocity, to, to_velocity)
+                });
+                if reachable {
+                    (to, to_velocity)
+                } else {
+                    let &(from, from_velocity, spring) = placed
+                        .iter()
+                        .min_by_key(|(from, ..)| from.delta_to(to).len())
+                        .unwrap();
+                    arc_from(spring).repair(from, from_velocity, to, to_velocity)
+                }
+            };
+            let spring = matches!(chunk_cloud.trigger, Some(TriggerKind::Spring));
+            placed.push((position, velocity, spring));
+            let position = position.shifted(-surface);
+            let cloud = self.clouds.insert(new_cloud(position, velocity));
+
+            if let Some(kind) = &chunk_cloud.trigger {
+                let trigger = match kind {
+                    TriggerKind::Spring => Trigger::spring(cloud, position),
+                    TriggerKind::Coin => Trigger::coin(cloud, position),
+                    TriggerKind::PowerUp(power_up) => Trigger::power_up(*power_up, cloud, position),
+                };
+                self.triggers.insert(trigger);
+            }
+
+            top_cloud = Some(cloud);
+        }
+        if let Some(cloud) = top_cloud {
+            self.last_generated_cloud = Some(cloud);
+        }
+
+        for &position in &chunk.coins {
+            self.triggers
+                .insert(Trigger::free_coin(origin.shifted(position)));
+        }
+
+        for bird in &chunk.birds {
+            self.scripted_birds.push(ScriptedBird {
+                spawn_height: origin.to_world().y + bird.spawn_height,
+                position: origin.shifted(bird.position),
+                speed: bird.speed,
+                kind: bird.kind,
+            });
+        }
+
+        self.generated_height = origin.to_world().y + chunk.height;
+    }
+
     pub fn despawn_below(&mut self) {
         let low = self.camera.center.to_world().y - self.camera.fov;
         for id in self.clouds.ids() {
@@ -98,3 +281,221 @@ impl Model {
         }
     }
 }
+
+/// Height of the top of the cloud above its anchor, in the middle where the doodle lands.
+fn surface_offset(collider: &Collider) -> Coord {
+    let height = collider.compute_aabb().height() + Coord::ONE;
+    collider
+        .cast_ray(
+            collider.position.shifted(vec2(Coord::ZERO, height)),
+            -vec2::UNIT_Y,
+            height * r32(2.0),
+        )
+        .map_or(Coord::ZERO, |hit| collider.position.delta_to(hit.point).y)
+}
+
+/// Sample a value uniformly from the range.
+fn gen_range(rng: &mut impl Rng, range: &RangeInclusive<R32>) -> R32 {
+    rng.gen_range(range.start().as_f32()..=range.end().as_f32())
+        .as_r32()
+}
+
+/// Returns `true` with the given probability.
+fn gen_chance(rng: &mut impl Rng, chance: R32) -> bool {
+    rng.gen_bool(chance.as_f32().clamp(0.0, 1.0).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cloud_anchors(model: &Model) -> Vec<vec2<Coord>> {
+        model
+            .clouds
+            .ids()
+            .into_iter()
+            .map(|id| get!(model.clouds, id, (&anchor)).unwrap().0.to_world())
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_generates_same_level() {
+        let mut a = test_model(42);
+        let mut b = test_model(42);
+        let mut c = test_model(43);
+        for model in [&mut a, &mut b, &mut c] {
+            model.generate_level(r32(1.0 / 120.0));
+        }
+        assert_eq!(cloud_anchors(&a), cloud_anchors(&b));
+        assert_ne!(cloud_anchors(&a), cloud_anchors(&c));
+    }
+
+    /// Time until a doodle jumping off a cloud falls back down to `height` above the takeoff.
+    fn air_time(config: &Config, height: Coord) -> Time {
+        let speed = config.doodle.jump;
+        let discriminant = (speed.sqr() - r32(2.0) * config.gravity * height).max(Coord::ZERO);
+        (speed + discriminant.sqrt()) / config.gravity
+    }
+
+    /// Put the doodle on the cloud `from`, jump towards the cloud `to` and steer to it,
+    /// returns whether it lands there. The clouds are indices into the snapshot.
+    fn jump_lands(snapshot: &Snapshot, from: usize, to: usize) -> bool {
+        let mut model = Model::from_snapshot(snapshot.clone());
+        let cloud_ids = model.clouds.ids();
+        let (from, to) = (cloud_ids[from], cloud_ids[to]);
+
+        // Leave only the two clouds and the spring to jump off, if any
+        for id in cloud_ids {
+            if id != from && id != to {
+                model.clouds.remove(id);
+            }
+        }
+        for id in model.triggers.ids() {
+            let (kind, attachment) = get!(model.triggers, id, (&kind, &attached_to)).unwrap();
+            let spring = matches!(kind, TriggerKind::Spring)
+                && attachment.as_ref().map_or(false, |a| a.cloud == from);
+            if !spring {
+                model.triggers.remove(id);
+            }
+        }
+        let spring = !model.triggers.ids().is_empty();
+        for id in model.birds.ids() {
+            model.birds.remove(id);
+        }
+        model.scripted_birds.clear();
+        model.next_bird = r32(1e6);
+        model.generated_height = r32(1e6);
+
+        let doodle = model.players[0].body.unwrap();
+        let (&from_pos, &from_velocity) = get!(
+            model.clouds,
+            from,
+            (&body.collider.position, &anchor_velocity)
+        )
+        .unwrap();
+        let (&to_velocity,) = get!(model.clouds, to, (&anchor_velocity)).unwrap();
+        let (collider, velocity) = get!(
+            model.doodles,
+            doodle,
+            (&mut body.collider, &mut body.velocity)
+        )
+        .unwrap();
+        *collider = Collider::new(
+            from_pos.shifted(vec2(0.0, 1.0).as_r32()),
+            collider.shape().clone(),
+        );
+        *velocity = vec2::ZERO;
+
+        let delta_time = r32(1.0 / 120.0);
+        // A spring launches the doodle by itself
+        let mut jumped = false;
+        let mut launched = false;
+        for _ in 0..720 {
+            let Some(doodle) = model.players[0].body else {
+                return false;
+            };
+            let (&position, &grounded) =
+                get!(model.doodles, doodle, (&body.collider.position, &grounded)).unwrap();
+            if launched && grounded == Some(to) {
+                return true;
+            }
+            let (&to_pos,) = get!(model.clouds, to, (&body.collider.position)).unwrap();
+            let delta = position.delta_to(to_pos);
+
+            let mut input = PlayerInput {
+                input_dir: vec2::ZERO,
+                jump: false,
+                shoot: false,
+                aim: Aim::Direction(vec2::UNIT_Y),
+            };
+            if launched {
+                // Follow the cloud, a little ahead if it moves
+                let lead = delta.x + to_velocity.x * r32(0.2);
+                input.input_dir.x = (lead / r32(0.5)).clamp_abs(Coord::ONE);
+            } else if grounded == Some(from) {
+                let (&from_pos,) = get!(model.clouds, from, (&body.collider.position)).unwrap();
+                input.input_dir.x =
+                    (position.delta_to(from_pos).x / r32(0.5)).clamp_abs(Coord::ONE);
+                if !spring {
+                    // Wait for a moving cloud to come around
+                    let time = air_time(&model.config, delta.y + r32(0.75));
+                    let offset = delta.x + (to_velocity.x - from_velocity.x) * time;
+                    jumped = from_velocity == to_velocity || offset.abs() < r32(0.5);
+                    input.jump = jumped;
+                }
+            }
+
+            model.update(&[input], delta_time);
+            launched = jumped
+                || model
+                    .events
+                    .iter()
+                    .any(|event| matches!(event, Event::SpringBounced { .. }));
+        }
+        false
+    }
+
+    #[test]
+    fn every_cloud_is_landed_on() {
+        for seed in 0..5 {
+            let mut model = test_model(seed);
+            let doodle = model.players[0].body.unwrap();
+            let (position,) = get!(model.doodles, doodle, (&mut body.collider.position)).unwrap();
+            // Climb high enough for the chunks and moving clouds to appear
+            *position = position.shifted(vec2(0.0, 120.0).as_r32());
+            model.generate_level(r32(1.0 / 120.0));
+            let snapshot = model.to_snapshot();
+
+            let clouds: Vec<Position> = snapshot.clouds.iter().map(|cloud| cloud.anchor).collect();
+            for (to, &to_anchor) in clouds.iter().enumerate() {
+                // The starting clouds are placed by hand
+                if to_anchor.to_world().y <= r32(2.5) {
+                    continue;
+                }
+                // Try jumping off the closest clouds below
+                let mut below: Vec<usize> = (0..clouds.len())
+                    .filter(|&from| {
+                        let height = clouds[from].delta_to(to_anchor).y;
+                        height > Coord::ZERO && height < r32(12.0)
+                    })
+                    .collect();
+                below.sort_by_key(|&from| clouds[from].delta_to(to_anchor).len());
+                let landed = below
+                    .into_iter()
+                    .take(3)
+                    .any(|from| jump_lands(&snapshot, from, to));
+                assert!(
+                    landed,
+                    "seed {seed}: cloud at {:?} cannot be landed on",
+                    to_anchor.to_world()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn triggers_spawn_on_their_clouds() {
+        let mut model = test_model(4);
+        model.generate_level(r32(1.0 / 120.0));
+        for (_, (collider, attachment)) in query!(model.triggers, (&collider, &attached_to)) {
+            // Otherwise the first tick would sweep and draw it from the origin
+            assert_eq!(collider.prev_position, collider.position);
+            if let Some(attachment) = attachment {
+                let (&cloud_pos,) =
+                    get!(model.clouds, attachment.cloud, (&body.collider.position)).unwrap();
+                assert_eq!(
+                    collider.position,
+                    cloud_pos.shifted(attachment.relative_pos)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn level_is_generated_ahead_of_the_player() {
+        let mut model = test_model(7);
+        model.generate_level(r32(1.0 / 120.0));
+        assert!(model.generated_height >= model.config.generation.generate_ahead);
+        assert!(cloud_anchors(&model).len() > 3);
+    }
+}
@@ -1,19 +1,34 @@
+mod birds;
 mod collision;
 mod control;
+mod events;
 mod generation;
 mod movement;
 mod particles;
+mod power_ups;
+mod reachability;
+
+pub use self::reachability::JumpArc;
 
 use super::*;
 
 impl Model {
-    pub fn update(&mut self, input: PlayerInput, delta_time: Time) {
+    /// Simulate a single tick, with an input for each player.
+    pub fn update(&mut self, inputs: &[PlayerInput], delta_time: Time) {
+        // Events of the previous tick, whether anyone took them or not
+        self.events.clear();
+        self.remember_positions();
         self.time += delta_time;
 
         self.generate_level(delta_time);
 
-        self.player_control(input, delta_time);
+        self.timers(delta_time);
+        for (player, input) in inputs.iter().enumerate() {
+            self.player_control(player, input, delta_time);
+        }
         self.gravity(delta_time);
+        self.power_ups(delta_time);
+        self.bird_behaviour(delta_time);
         self.movement(delta_time);
 
         self.collide_clouds(delta_time);
@@ -23,15 +38,77 @@ impl Model {
         self.collide_triggers(delta_time);
 
         self.camera_control(delta_time);
+        self.update_stats();
 
+        self.process_events();
         self.update_particles(delta_time);
         self.lifetime(delta_time);
         self.check_ded();
         self.despawn_below();
+        self.update_query_grid();
+    }
+
+    /// Store current positions to interpolate rendering between ticks.
+    fn remember_positions(&mut self) {
+        self.camera.prev_center = self.camera.center;
+        for id in self.doodles.ids() {
+            let (prev, &pos) = get!(
+                self.doodles,
+                id,
+                (&mut body.collider.prev_position, &body.collider.position)
+            )
+            .unwrap();
+            *prev = pos;
+        }
+        for id in self.birds.ids() {
+            let (prev, &pos) = get!(
+                self.birds,
+                id,
+                (&mut body.collider.prev_position, &body.collider.position)
+            )
+            .unwrap();
+            *prev = pos;
+        }
+        for id in self.clouds.ids() {
+            let (prev, &pos) = get!(
+                self.clouds,
+                id,
+                (&mut body.collider.prev_position, &body.collider.position)
+            )
+            .unwrap();
+            *prev = pos;
+        }
+        for id in self.projectiles.ids() {
+            let (prev, &pos) = get!(
+                self.projectiles,
+                id,
+                (&mut body.collider.prev_position, &body.collider.position)
+            )
+            .unwrap();
+            *prev = pos;
+        }
+        for id in self.triggers.ids() {
+            let (prev, &pos) = get!(
+                self.triggers,
+                id,
+                (&mut collider.prev_position, &collider.position)
+            )
+            .unwrap();
+            *prev = pos;
+        }
+        for id in self.particles.ids() {
+            let (prev, &pos) = get!(
+                self.particles,
+                id,
+                (&mut body.collider.prev_position, &body.collider.position)
+            )
+            .unwrap();
+            *prev = pos;
+        }
     }
 
     fn gravity(&mut self, delta_time: Time) {
-        let gravity = vec2(0.0, -9.8).as_r32() * delta_time;
+        let gravity = -vec2::UNIT_Y * self.config.gravity * delta_time;
 
         for id in self.doodles.ids() {
             let (velocity,) = get!(self.doodles, id, (&mut body.velocity)).unwrap();
@@ -39,6 +116,25 @@ impl Model {
         }
     }
 
+    fn update_stats(&mut self) {
+        for player in &mut self.players {
+            let Some(body) = player.body else {
+                continue;
+            };
+            let (&position,) = get!(self.doodles, body, (&body.collider.position)).unwrap();
+            player.stats.max_height = player.stats.max_height.max(position.to_world().y);
+        }
+    }
+
+    fn timers(&mut self, delta_time: Time) {
+        for id in self.doodles.ids() {
+            let (coyote_time, shoot_cooldown) =
+                get!(self.doodles, id, (&mut coyote_time, &mut shoot_cooldown)).unwrap();
+            *coyote_time = (*coyote_time - delta_time).max(Time::ZERO);
+            *shoot_cooldown = (*shoot_cooldown - delta_time).max(Time::ZERO);
+        }
+    }
+
     fn lifetime(&mut self, delta_time: Time) {
         for id in self.projectiles.ids() {
             let (lifetime,) = get!(self.projectiles, id, (&mut lifetime)).unwrap();
@@ -62,18 +158,142 @@ impl Model {
     }
 
     // LOL
+    /// The camera follows the leader, so a player that lags behind
+    /// and falls off the bottom of the screen is eliminated.
     fn check_ded(&mut self) {
-        for id in self.doodles.ids() {
+        for player in &mut self.players {
+            let Some(id) = player.body else {
+                continue;
+            };
             let (&pos,) = get!(self.doodles, id, (&body.collider.position)).unwrap();
             if pos.delta_to(self.camera.center).y > self.camera.fov / r32(2.0) + r32(1.0) {
-                self.transition = Some(geng::state::Transition::Switch(Box::new(
-                    crate::end_screen::EndScreen::new(
-                        &self.geng,
-                        &self.assets,
-                        self.current_score(),
-                    ),
-                )));
+                player.body = None;
+                self.doodles.remove(id);
+                self.events.push(Event::Died {
+                    doodle: id,
+                    score: player.total_score(),
+                });
             }
         }
     }
 }
+
+/// A single player model with the game's own config and chunks.
+#[cfg(test)]
+pub(crate) fn test_model(seed: u64) -> Model {
+    test_model_with_players(seed, 1)
+}
+
+/// Like `test_model`, but with the given number of players.
+#[cfg(test)]
+pub(crate) fn test_model_with_players(seed: u64, players: usize) -> Model {
+    let config = ron::from_str(include_str!("../../../assets/config.ron")).unwrap();
+    let chunks = ron::from_str(include_str!("../../../assets/chunks.ron")).unwrap();
+    Model::new(config, chunks, seed, players)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idle() -> PlayerInput {
+        PlayerInput {
+            input_dir: vec2::ZERO,
+            jump: false,
+            shoot: false,
+            aim: Aim::Direction(vec2::UNIT_X),
+        }
+    }
+
+    /// Two players at `(-0.75, 0)` and `(0.75, 0)`, with nothing generated around them.
+    fn two_players() -> Model {
+        let mut model = test_model_with_players(0, 2);
+        model.generated_height = r32(1e6);
+        model
+    }
+
+    #[test]
+    fn eliminated_player_does_not_stop_the_others() {
+        let mut model = two_players();
+        let width = model.world_width;
+        // Only the first player has a cloud to stand on
+        let cloud = model.clouds.insert(Cloud::new(Position::from_world(
+            vec2(-0.75, -0.75).as_r32(),
+            width,
+        )));
+        let standing = model.players[0].body.unwrap();
+        let falling = model.players[1].body.unwrap();
+
+        let delta_time = r32(1.0 / 120.0);
+        let mut died = Vec::new();
+        for _ in 0..600 {
+            if !model.players[1].is_alive() {
+                break;
+            }
+            model.update(&[idle(), idle()], delta_time);
+            died.extend(model.events.iter().filter_map(|event| match *event {
+                Event::Died { doodle, .. } => Some(doodle),
+                _ => None,
+            }));
+        }
+        assert_eq!(died, [falling]);
+        assert_eq!(model.players[1].body, None);
+        assert!(!model.doodles.ids().contains(&falling));
+        assert!(model.players[0].is_alive());
+        assert!(!model.is_game_over());
+        assert_eq!(model.leader(), Some(standing));
+
+        // The remaining player keeps playing
+        let (&grounded,) = get!(model.doodles, standing, (&grounded)).unwrap();
+        assert_eq!(grounded, Some(cloud));
+        let jump = PlayerInput {
+            jump: true,
+            ..idle()
+        };
+        model.update(&[jump.clone(), jump], delta_time);
+        assert!(model
+            .events
+            .iter()
+            .any(|event| matches!(event, Event::Jumped { doodle, .. } if *doodle == standing)));
+        assert!(model.players[0].is_alive());
+    }
+
+    #[test]
+    fn players_score_separately() {
+        let mut model = two_players();
+        let position = |id: Option<Id>| {
+            *get!(model.doodles, id.unwrap(), (&body.collider.position))
+                .unwrap()
+                .0
+        };
+        let first_pos = position(model.players[0].body);
+        let second_pos = position(model.players[1].body);
+
+        // The first player collects a coin and gets hit by a bird
+        model.triggers.insert(Trigger::free_coin(first_pos));
+        model.birds.insert(Bird::new(first_pos, 0.0));
+        // The second player shoots a bird to the right
+        let target = second_pos.shifted(vec2(3.0, 0.0).as_r32());
+        let target = model.birds.insert(Bird::new(target, 0.0));
+
+        let delta_time = r32(1.0 / 120.0);
+        let shoot = PlayerInput {
+            shoot: true,
+            ..idle()
+        };
+        model.update(&[idle(), shoot], delta_time);
+        for _ in 0..60 {
+            if !model.birds.ids().contains(&target) {
+                break;
+            }
+            model.update(&[idle(), idle()], delta_time);
+        }
+        assert!(model.birds.ids().is_empty());
+
+        assert_eq!(model.players[0].score, 100 - 50);
+        assert_eq!(model.players[0].stats.coins_collected, 1);
+        assert_eq!(model.players[1].score, 100);
+        assert_eq!(model.players[1].stats.coins_collected, 0);
+        assert!(model.players.iter().all(Player::is_alive));
+    }
+}
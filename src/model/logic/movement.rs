@@ -10,7 +10,7 @@ impl Model {
                 (&mut body.collider.position, &mut body.velocity)
             )
             .unwrap();
-            *velocity = velocity.clamp_len(..=40.0.as_r32());
+            *velocity = velocity.clamp_len(..=self.config.doodle.max_speed);
             position.shift(*velocity * delta_time);
         }
 
@@ -26,6 +26,7 @@ impl Model {
         }
 
         // Clouds
+        let config = &self.config.cloud;
         for id in self.clouds.ids() {
             let (position, velocity, anchor, &anchor_velocity) = get!(
                 self.clouds,
@@ -43,20 +44,18 @@ impl Model {
             anchor.shift(anchor_velocity * delta_time);
 
             // Dampen
-            let damp = 10.0.as_r32();
-            let damp = velocity.clamp_len(..=Coord::ONE) * damp * delta_time;
+            let damp = velocity.clamp_len(..=Coord::ONE) * config.damping * delta_time;
             *velocity -= damp;
 
             // Move towards the anchor
             let direction = position.delta_to(*anchor);
-            let elasticity = 200.0.as_r32();
             *velocity += direction.normalize_or_zero()
                 * direction.len().sqr().min(10.0.as_r32())
-                * elasticity
+                * config.elasticity
                 * delta_time;
 
             position.shift((*velocity + anchor_velocity) * delta_time);
-            *position = anchor.shifted(anchor.delta_to(*position).clamp_len(..=5.0.as_r32()))
+            *position = anchor.shifted(anchor.delta_to(*position).clamp_len(..=config.max_offset))
         }
 
         // Projectiles
@@ -92,3 +91,43 @@ impl Model {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doodle_moves_across_the_seam() {
+        let mut model = test_model(0);
+        let width = model.world_width;
+        let doodle = model.players[0].body.unwrap();
+        let (position, velocity) = get!(
+            model.doodles,
+            doodle,
+            (&mut body.collider.position, &mut body.velocity)
+        )
+        .unwrap();
+        *position = Position::from_world(vec2(width - r32(0.1), r32(5.0)), width);
+        *velocity = vec2(r32(2.0), r32(-1.0));
+
+        model.movement(r32(0.1));
+
+        let (&position,) = get!(model.doodles, doodle, (&body.collider.position)).unwrap();
+        let position = position.to_world();
+        assert!((position.x - r32(0.1)).abs() < r32(1e-4), "{position:?}");
+        assert!((position.y - r32(4.9)).abs() < r32(1e-4), "{position:?}");
+    }
+
+    #[test]
+    fn doodle_speed_is_limited() {
+        let mut model = test_model(0);
+        let doodle = model.players[0].body.unwrap();
+        let (velocity,) = get!(model.doodles, doodle, (&mut body.velocity)).unwrap();
+        *velocity = vec2(R32::ZERO, r32(-1000.0));
+
+        model.movement(r32(0.01));
+
+        let (&velocity,) = get!(model.doodles, doodle, (&body.velocity)).unwrap();
+        assert!(velocity.len() <= model.config.doodle.max_speed + r32(1e-3));
+    }
+}
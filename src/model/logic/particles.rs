@@ -8,7 +8,7 @@ impl Model {
         velocity: vec2<Coord>,
         color: Color,
     ) {
-        let mut rng = thread_rng();
+        let rng = &mut self.particle_rng;
         let position_radius = r32(0.2);
 
         let amount = if intensity.as_f32() < 1.0 {
@@ -43,3 +43,18 @@ impl Model {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn particles_do_not_affect_gameplay_rng() {
+        let mut with_particles = test_model(5);
+        let mut without = test_model(5);
+        let position = Position::zero(with_particles.world_width);
+        with_particles.spawn_particles(r32(10.0), position, vec2::ZERO, Color::WHITE);
+
+        assert_eq!(with_particles.rng.gen::<u64>(), without.rng.gen::<u64>());
+    }
+}
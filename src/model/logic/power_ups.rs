@@ -0,0 +1,214 @@
+use super::*;
+
+impl Model {
+    /// Apply the effects of the active power-ups and run down their timers.
+    pub fn power_ups(&mut self, delta_time: Time) {
+        let config = &self.config.power_up;
+        let mut magnets = Vec::new();
+        let mut trails = Vec::new();
+        for id in self.doodles.ids() {
+            let (&position, velocity, power_ups) = get!(
+                self.doodles,
+                id,
+                (&body.collider.position, &mut body.velocity, &mut power_ups)
+            )
+            .unwrap();
+            power_ups.tick(delta_time);
+
+            if power_ups.is_active(PowerUp::Jetpack) {
+                velocity.y = velocity.y.max(config.jetpack_speed);
+            }
+            if power_ups.is_active(PowerUp::FeatherFall) {
+                velocity.y = velocity.y.max(-config.feather_fall_speed);
+            }
+            if power_ups.is_active(PowerUp::Magnet) {
+                magnets.push(position);
+            }
+            for active in power_ups.iter() {
+                trails.push((position, -*velocity * r32(0.2), active.kind.color()));
+            }
+        }
+
+        // Pull the coins towards the closest doodle with a magnet
+        for id in self.triggers.ids() {
+            let (kind, position, attachment) = get!(
+                self.triggers,
+                id,
+                (&kind, &mut collider.position, &mut attached_to)
+            )
+            .unwrap();
+            if !matches!(kind, TriggerKind::Coin) {
+                continue;
+            }
+            let Some(delta) = magnets
+                .iter()
+                .map(|&magnet| position.delta_to(magnet))
+                .filter(|delta| delta.len() < config.magnet_radius)
+                .min_by_key(|delta| delta.len())
+            else {
+                continue;
+            };
+            *attachment = None;
+            position.shift(delta.clamp_len(..=config.magnet_speed * delta_time));
+        }
+
+        for (position, velocity, color) in trails {
+            self.spawn_particles(r32(0.3), position, velocity, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELTA_TIME: f32 = 1.0 / 120.0;
+
+    /// A doodle at the origin above a cloud at `cloud_y`, with nothing else around.
+    fn doodle_above_cloud(cloud_y: f32) -> (Model, Id) {
+        let mut model = test_model(0);
+        model.generated_height = r32(1e6);
+        let width = model.world_width;
+        model.clouds.insert(Cloud::new(Position::from_world(
+            vec2(0.0, cloud_y).as_r32(),
+            width,
+        )));
+        let doodle = model.players[0].body.unwrap();
+        (model, doodle)
+    }
+
+    fn activate(model: &mut Model, doodle: Id, kind: PowerUp) {
+        let duration = model.config.power_up.duration(kind);
+        let (power_ups,) = get!(model.doodles, doodle, (&mut power_ups)).unwrap();
+        power_ups.activate(kind, duration);
+    }
+
+    fn is_active(model: &Model, doodle: Id, kind: PowerUp) -> bool {
+        let (power_ups,) = get!(model.doodles, doodle, (&power_ups)).unwrap();
+        power_ups.is_active(kind)
+    }
+
+    fn idle() -> PlayerInput {
+        PlayerInput {
+            input_dir: vec2::ZERO,
+            jump: false,
+            shoot: false,
+            aim: Aim::Direction(vec2::UNIT_X),
+        }
+    }
+
+    /// Update the model for the given time, returns all the events produced.
+    fn run(model: &mut Model, time: f32, input: &PlayerInput) -> Vec<Event> {
+        let mut events = Vec::new();
+        for _ in 0..(time / DELTA_TIME).round() as usize {
+            model.update(std::slice::from_ref(input), r32(DELTA_TIME));
+            events.append(&mut model.events);
+        }
+        events
+    }
+
+    /// Check that the power-up is still active shortly before its duration ends,
+    /// and gone shortly after.
+    fn assert_expires(model: &mut Model, doodle: Id, kind: PowerUp, elapsed: f32) {
+        let duration = model.config.power_up.duration(kind).as_f32();
+        run(model, duration - elapsed - 0.1, &idle());
+        assert!(is_active(model, doodle, kind), "{kind:?} expired too early");
+        run(model, 0.2, &idle());
+        assert!(!is_active(model, doodle, kind), "{kind:?} did not expire");
+    }
+
+    fn velocity(model: &Model, doodle: Id) -> vec2<Coord> {
+        *get!(model.doodles, doodle, (&body.velocity)).unwrap().0
+    }
+
+    #[test]
+    fn jetpack_lifts_the_doodle() {
+        let (mut model, doodle) = doodle_above_cloud(-0.75);
+        activate(&mut model, doodle, PowerUp::Jetpack);
+
+        run(&mut model, 1.0, &idle());
+        let speed = model.config.power_up.jetpack_speed;
+        assert_eq!(velocity(&model, doodle).y, speed);
+        let (&position,) = get!(model.doodles, doodle, (&body.collider.position)).unwrap();
+        assert!(position.to_world().y > speed * r32(0.9));
+
+        assert_expires(&mut model, doodle, PowerUp::Jetpack, 1.0);
+        assert!(velocity(&model, doodle).y < speed);
+    }
+
+    #[test]
+    fn shield_absorbs_a_bird_hit() {
+        let (mut model, doodle) = doodle_above_cloud(-0.75);
+        activate(&mut model, doodle, PowerUp::Shield);
+        assert_expires(&mut model, doodle, PowerUp::Shield, 0.0);
+
+        activate(&mut model, doodle, PowerUp::Shield);
+        let (&position,) = get!(model.doodles, doodle, (&body.collider.position)).unwrap();
+        model.birds.insert(Bird::new(position, 0.0));
+        let events = run(&mut model, DELTA_TIME, &idle());
+
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, Event::ShieldBroken { .. })));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, Event::BirdHitPlayer { .. })));
+        assert!(!is_active(&model, doodle, PowerUp::Shield));
+        assert_eq!(model.players[0].score, 0);
+    }
+
+    #[test]
+    fn magnet_pulls_coins_in_range() {
+        let (mut model, doodle) = doodle_above_cloud(-0.75);
+        let width = model.world_width;
+        let at = |x: f32| Position::from_world(vec2(x, 0.0).as_r32(), width);
+        activate(&mut model, doodle, PowerUp::Magnet);
+        model.triggers.insert(Trigger::free_coin(at(3.0)));
+        let far = model.triggers.insert(Trigger::free_coin(at(-7.0)));
+
+        let events = run(&mut model, 1.0, &idle());
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, Event::CoinCollected { .. })));
+        assert_eq!(model.players[0].stats.coins_collected, 1);
+        let (&far_pos,) = get!(model.triggers, far, (&collider.position)).unwrap();
+        assert_eq!(far_pos, at(-7.0));
+
+        assert_expires(&mut model, doodle, PowerUp::Magnet, 1.0);
+        let coin = model.triggers.insert(Trigger::free_coin(at(3.0)));
+        run(&mut model, 1.0, &idle());
+        let (&coin_pos,) = get!(model.triggers, coin, (&collider.position)).unwrap();
+        assert_eq!(coin_pos, at(3.0));
+    }
+
+    #[test]
+    fn rapid_fire_shortens_the_cooldown() {
+        let (mut model, doodle) = doodle_above_cloud(-0.75);
+        let shoot = PlayerInput {
+            shoot: true,
+            ..idle()
+        };
+        let cooldown = |model: &Model| *get!(model.doodles, doodle, (&shoot_cooldown)).unwrap().0;
+
+        activate(&mut model, doodle, PowerUp::RapidFire);
+        run(&mut model, DELTA_TIME, &shoot);
+        assert_eq!(cooldown(&model), model.config.power_up.rapid_fire_cooldown);
+
+        assert_expires(&mut model, doodle, PowerUp::RapidFire, DELTA_TIME);
+        run(&mut model, DELTA_TIME, &shoot);
+        assert_eq!(cooldown(&model), model.config.doodle.shoot_cooldown);
+    }
+
+    #[test]
+    fn feather_fall_limits_the_falling_speed() {
+        let (mut model, doodle) = doodle_above_cloud(-3.0);
+        activate(&mut model, doodle, PowerUp::FeatherFall);
+
+        run(&mut model, 0.5, &idle());
+        let speed = model.config.power_up.feather_fall_speed;
+        assert_eq!(velocity(&model, doodle).y, -speed);
+
+        // Landed on the cloud by now
+        assert_expires(&mut model, doodle, PowerUp::FeatherFall, 0.5);
+    }
+}
@@ -0,0 +1,133 @@
+use super::*;
+
+/// Describes how far the doodle can get with a single jump off a cloud.
+#[derive(Debug, Clone, Copy)]
+pub struct JumpArc {
+    /// Vertical takeoff speed, the impulse applied by `Model::player_control`.
+    pub jump_speed: Coord,
+    pub gravity: Coord,
+    pub move_speed: Coord,
+    pub acceleration: Coord,
+    /// Maximum horizontal distance between the doodle and the cloud centers
+    /// at which the doodle still lands on the cloud.
+    pub landing_width: Coord,
+    /// Fraction of the theoretical reach that is considered safe.
+    pub safety: R32,
+    pub world_width: Coord,
+    /// How long the player can be expected to wait on a cloud
+    /// for a moving cloud to come into reach.
+    pub max_wait: Time,
+}
+
+impl JumpArc {
+    pub fn new(config: &Config, world_width: Coord, landing_width: Coord) -> Self {
+        Self {
+            jump_speed: config.doodle.jump,
+            gravity: config.gravity,
+            move_speed: config.doodle.move_speed,
+            acceleration: config.doodle.acceleration,
+            landing_width,
+            safety: config.generation.jump_safety,
+            world_width,
+            max_wait: config.generation.max_wait,
+        }
+    }
+
+    /// The arc of a jump off a spring.
+    pub fn spring(self, config: &Config) -> Self {
+        Self {
+            jump_speed: self.jump_speed.max(config.doodle.spring_min_speed),
+            ..self
+        }
+    }
+
+    /// The maximum height gain that is considered safe to reach.
+    pub fn max_height(&self) -> Coord {
+        self.jump_speed.sqr() / (r32(2.0) * self.gravity) * self.safety
+    }
+
+    /// Time from the takeoff until the doodle falls down to `height` relative to the takeoff.
+    /// Returns `None` if the height cannot be reached safely.
+    pub fn air_time(&self, height: Coord) -> Option<Time> {
+        if height > self.max_height() {
+            return None;
+        }
+        let discriminant = self.jump_speed.sqr() - r32(2.0) * self.gravity * height;
+        Some((self.jump_speed + discriminant.max(R32::ZERO).sqrt()) / self.gravity)
+    }
+
+    /// Horizontal distance that is considered safe to cover in the given time,
+    /// starting from rest.
+    pub fn horizontal_reach(&self, time: Time) -> Coord {
+        let speedup_time = self.move_speed / self.acceleration;
+        let distance = if time < speedup_time {
+            self.acceleration * time.sqr() / r32(2.0)
+        } else {
+            self.move_speed * (time - speedup_time / r32(2.0))
+        };
+        distance * self.safety
+    }
+
+    /// Minimum relative speed at which a moving cloud is guaranteed
+    /// to come into the given reach within the waiting time.
+    fn min_relative_speed(&self, reach: Coord) -> Coord {
+        (self.world_width - reach * r32(2.0)).max(Coord::ZERO) / self.max_wait
+    }
+
+    /// Check whether a cloud at `to` can be reached by jumping off a cloud at `from`.
+    /// The velocities are the velocities of the clouds' anchors,
+    /// only their horizontal part is taken into account.
+    pub fn can_reach(
+        &self,
+        from: Position,
+        from_velocity: vec2<Coord>,
+        to: Position,
+        to_velocity: vec2<Coord>,
+    ) -> bool {
+        let delta = from.delta_to(to);
+        let Some(time) = self.air_time(delta.y) else {
+            return false;
+        };
+        let reach = self.horizontal_reach(time) + self.landing_width;
+        let relative_speed = (to_velocity.x - from_velocity.x).abs();
+        if relative_speed == Coord::ZERO {
+            return delta.x.abs() <= reach;
+        }
+        // The clouds move relative to each other, and their offset by the time
+        // the player gets to `from` is unknown. So while waiting the offset has to
+        // go around the whole cylinder for the target to come into reach.
+        relative_speed >= self.min_relative_speed(reach)
+    }
+
+    /// Returns the closest position and velocity to `to` and `to_velocity`
+    /// of a cloud that can be reached from `from`.
+    pub fn repair(
+        &self,
+        from: Position,
+        from_velocity: vec2<Coord>,
+        to: Position,
+        to_velocity: vec2<Coord>,
+    ) -> (Position, vec2<Coord>) {
+        if self.can_reach(from, from_velocity, to, to_velocity) {
+            return (to, to_velocity);
+        }
+
+        let mut delta = from.delta_to(to);
+        delta.y = delta.y.min(self.max_height());
+        let mut velocity = to_velocity;
+        if let Some(time) = self.air_time(delta.y) {
+            let reach = self.horizontal_reach(time) + self.landing_width;
+            let relative = to_velocity.x - from_velocity.x;
+            if relative == Coord::ZERO {
+                delta.x = delta.x.clamp_abs(reach);
+            } else {
+                // Speed the cloud up, so it comes into reach in time,
+                // with a little margin for rounding errors
+                let min_speed = self.min_relative_speed(reach) * r32(1.01);
+                let speed = relative.abs().max(min_speed);
+                velocity.x = from_velocity.x + relative / relative.abs() * speed;
+            }
+        }
+        (from.shifted(delta), velocity)
+    }
+}
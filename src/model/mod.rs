@@ -1,82 +1,149 @@
+mod broadphase;
 mod camera;
+mod chunk;
 mod components;
+mod config;
+mod curve;
+mod event;
 mod logic;
 mod player;
+mod query;
+mod snapshot;
+mod stats;
 
-pub use self::{camera::*, components::*, player::*};
+pub use self::{
+    broadphase::*, camera::*, chunk::*, components::*, config::*, curve::*, event::*,
+    logic::JumpArc, player::*, query::*, snapshot::*, stats::*,
+};
 
 use crate::prelude::*;
 
+use rand_chacha::ChaCha8Rng;
+
 pub type Time = R32;
 pub type Coord = R32;
 pub type Position = PositionCylinder<Coord>;
 
 pub struct Model {
+    pub config: Config,
+    /// Hand-authored level segments to mix into the generation.
+    pub chunks: Vec<Chunk>,
+    /// The seed the world was generated from.
+    pub seed: u64,
+    rng: ChaCha8Rng,
+    /// Randomness for the cosmetic effects, so they do not affect the gameplay.
+    particle_rng: ChaCha8Rng,
     pub time: Time,
-    /// Use `current_score()` to get accurate score
-    score: i32,
     pub world_width: Coord,
     /// The height up to which the world has been generated so far.
     pub generated_height: Coord,
+    /// The highest generated cloud, that the next one has to be reachable from.
+    pub last_generated_cloud: Option<Id>,
+    /// Birds placed by chunks that have not spawned yet.
+    pub scripted_birds: Vec<ScriptedBird>,
     /// The time until the next bird spawns.
     pub next_bird: Time,
     pub camera: Camera,
-    pub player: Player,
+    /// All players, eliminated ones included, so that indices stay the same.
+    pub players: Vec<Player>,
     pub doodles: StructOf<Arena<Doodle>>,
     pub birds: StructOf<Arena<Bird>>,
     pub clouds: StructOf<Arena<Cloud>>,
     pub projectiles: StructOf<Arena<Projectile>>,
     pub triggers: StructOf<Arena<Trigger>>,
     pub particles: StructOf<Arena<Particle>>,
-    geng: Geng,
-    pub assets: Rc<Assets>,
-    pub shhh: Option<geng::SoundEffect>,
+    /// Whether the cloud sliding sound should be playing.
+    pub shhh_playing: bool,
     pub shhh_volume: f64,
-    pub transition: Option<geng::state::Transition>,  
+    /// Events produced during the last tick.
+    pub events: Vec<Event>,
+    query_grid: QueryGrid,
 }
 
 impl Model {
-    pub fn new(geng: Geng, assets: Rc<Assets>) -> Self {
-        let world_width = (35.0 * 0.55 ).as_r32();
+    pub fn new(config: Config, chunks: Vec<Chunk>, seed: u64, players: usize) -> Self {
+        let world_width = (35.0 * 0.55).as_r32();
 
         let mut doodles: StructOf<Arena<Doodle>> = default();
-        let player_body = doodles.insert(Doodle::new(Body::new(
-            Collider::new(Position::zero(world_width), Shape::rectangle(1.0, 1.0)),
-            10.0,
-        )));
-        Self {
-            geng, 
-            transition: None,
+        let players = (0..players)
+            .map(|i| {
+                // Spread the players out, centered around zero
+                let x = (i as f32 - (players - 1) as f32 / 2.0) * 1.5;
+                let position = Position::from_world(vec2(x, 0.0).as_r32(), world_width);
+                Player::new(doodles.insert(Doodle::new(Body::new(
+                    Collider::new(position, Shape::capsule(0.05, 0.45)),
+                    10.0,
+                ))))
+            })
+            .collect();
+        let mut model = Self {
+            config,
+            chunks,
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            particle_rng: {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                rng.set_stream(1);
+                rng
+            },
+            shhh_playing: false,
             shhh_volume: 0.0,
-            shhh: None,
-            assets,
+            events: Vec::new(),
             time: Time::ZERO,
-            score: 0,
             world_width,
             generated_height: Coord::ZERO,
+            last_generated_cloud: None,
+            scripted_birds: Vec::new(),
             next_bird: Time::ZERO,
             // -3 so the clouds dont teleport (visibly) from one edge of the screen to the other
             // but disappear behind the edge instead
             // // * 0.55 because it was too wide Kapp
             camera: Camera::new(
-                (world_width.as_f32() - 3.0) 
+                (world_width.as_f32() - 3.0)
                     // * 0.55
-                    * 9.0 / 16.0,
+                    * 9.0
+                    / 16.0,
                 world_width,
             ),
-            player: Player { body: player_body },
+            players,
             doodles,
             birds: default(),
             clouds: default(),
             projectiles: default(),
             triggers: default(),
             particles: default(),
-        }
-    } 
+            query_grid: QueryGrid::new(world_width),
+        };
+        model.update_query_grid();
+        model
+    }
+
+    /// The body of the highest player still in the game.
+    pub fn leader(&self) -> Option<Id> {
+        self.players
+            .iter()
+            .filter_map(|player| player.body)
+            .max_by_key(|&id| {
+                let (position,) = get!(self.doodles, id, (&body.collider.position)).unwrap();
+                position.to_world().y
+            })
+    }
+
+    /// Index of the player controlling the doodle.
+    pub fn player_of(&self, doodle: Id) -> Option<usize> {
+        self.players
+            .iter()
+            .position(|player| player.body == Some(doodle))
+    }
+
+    /// The game is over once every player has been eliminated.
+    pub fn is_game_over(&self) -> bool {
+        !self.players.iter().any(Player::is_alive)
+    }
 
-    pub fn current_score(&self) -> i32 {
-        let height = self.camera.center.to_world().y.as_f32();
-        let height = (height * 5.0).floor() as i32;
-        self.score + height
+    /// Take the events produced during the last tick.
+    /// They are cleared at the start of every tick, so taking them is optional.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
     }
 }
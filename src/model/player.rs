@@ -1,11 +1,46 @@
 use super::*;
 
 pub struct Player {
-    pub body: Id,
+    /// `None` once the player has been eliminated.
+    pub body: Option<Id>,
+    /// Points from coins and birds, without the height bonus.
+    pub score: i32,
+    pub stats: Stats,
 }
 
+impl Player {
+    pub fn new(body: Id) -> Self {
+        Self {
+            body: Some(body),
+            score: 0,
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.body.is_some()
+    }
+
+    /// The score including the height bonus.
+    pub fn total_score(&self) -> i32 {
+        let height = self.stats.max_height.as_f32();
+        self.score + (height * 5.0).floor() as i32
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlayerInput {
     pub input_dir: vec2<Coord>,
     pub jump: bool,
     pub shoot: bool,
+    pub aim: Aim,
+}
+
+/// Where to shoot.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Aim {
+    /// Shoot at the position, e.g. the mouse cursor.
+    Position(Position),
+    /// Shoot in the direction, e.g. of the gamepad stick.
+    Direction(vec2<Coord>),
 }
@@ -0,0 +1,301 @@
+use super::*;
+
+/// Kind of an entity found by a world query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntityKind {
+    Doodle,
+    Cloud,
+    Bird,
+    Projectile,
+    Trigger,
+}
+
+/// Which entities a world query can hit.
+#[derive(Debug, Clone, Copy)]
+pub struct QueryFilter<'a> {
+    pub kinds: &'a [EntityKind],
+    /// An entity to ignore, usually the one doing the query.
+    pub exclude: Option<(EntityKind, Id)>,
+}
+
+impl<'a> QueryFilter<'a> {
+    pub fn only(kinds: &'a [EntityKind]) -> Self {
+        Self {
+            kinds,
+            exclude: None,
+        }
+    }
+
+    pub fn excluding(self, kind: EntityKind, id: Id) -> Self {
+        Self {
+            exclude: Some((kind, id)),
+            ..self
+        }
+    }
+
+    fn accepts(&self, kind: EntityKind, id: Id) -> bool {
+        self.kinds.contains(&kind) && self.exclude != Some((kind, id))
+    }
+}
+
+/// The first entity hit by a ray or a shape cast.
+#[derive(Debug, Clone, Copy)]
+pub struct QueryHit {
+    pub kind: EntityKind,
+    pub id: Id,
+    /// Distance travelled along the cast direction before the hit.
+    pub distance: Coord,
+    pub point: Position,
+    /// Normal of the entity's surface at the hit point.
+    pub normal: vec2<Coord>,
+}
+
+/// The broadphase grid of all the entities that queries can hit.
+/// Rebuilt at the end of every tick, so entities spawned during the tick
+/// are not found until then.
+pub struct QueryGrid {
+    grid: Broadphase,
+    entries: Vec<(EntityKind, Id)>,
+}
+
+impl QueryGrid {
+    pub fn new(world_width: Coord) -> Self {
+        Self {
+            grid: Broadphase::new(world_width),
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl Model {
+    /// Cast a ray from `origin` in the `direction` and return the first entity it hits
+    /// within `max_distance`. The ray wraps around the cylinder.
+    pub fn raycast(
+        &self,
+        origin: Position,
+        direction: vec2<Coord>,
+        max_distance: Coord,
+        filter: QueryFilter,
+    ) -> Option<QueryHit> {
+        let motion = direction.normalize_or_zero() * max_distance;
+        self.cast(filter, origin, motion, Coord::ZERO, |collider| {
+            collider.cast_ray(origin, direction, max_distance)
+        })
+    }
+
+    /// Move the `shape` from `origin` in the `direction` and return the first entity
+    /// it touches within `max_distance`. The cast wraps around the cylinder.
+    pub fn shape_cast(
+        &self,
+        shape: &Shape,
+        origin: Position,
+        direction: vec2<Coord>,
+        max_distance: Coord,
+        filter: QueryFilter,
+    ) -> Option<QueryHit> {
+        let motion = direction.normalize_or_zero() * max_distance;
+        let size = Collider::new(origin, shape.clone()).compute_aabb().size();
+        self.cast(filter, origin, motion, size.x.max(size.y), |collider| {
+            collider.cast_shape(shape, origin, direction, max_distance)
+        })
+    }
+
+    /// Find the first hit among the colliders near the segment from `origin` by `motion`,
+    /// widened by `radius`, using the query grid.
+    fn cast(
+        &self,
+        filter: QueryFilter,
+        origin: Position,
+        motion: vec2<Coord>,
+        radius: Coord,
+        cast: impl Fn(&Collider) -> Option<CastHit>,
+    ) -> Option<QueryHit> {
+        self.query_grid
+            .grid
+            .query_segment(origin, motion, radius)
+            .into_iter()
+            .map(|index| self.query_grid.entries[index])
+            .filter(|&(kind, id)| filter.accepts(kind, id))
+            .filter_map(|(kind, id)| {
+                // The entity might have despawned since the grid was built
+                let collider = self.collider(kind, id)?;
+                cast(collider).map(|hit| QueryHit {
+                    kind,
+                    id,
+                    distance: hit.distance,
+                    point: hit.point,
+                    normal: hit.normal,
+                })
+            })
+            .min_by_key(|hit| hit.distance)
+    }
+
+    /// Rebuild the grid used by queries from the current colliders.
+    pub(super) fn update_query_grid(&mut self) {
+        let mut grid = Broadphase::new(self.world_width);
+        let mut entries = Vec::new();
+        for (index, (kind, id, collider)) in self.colliders().enumerate() {
+            grid.insert(index, collider);
+            entries.push((kind, id));
+        }
+        self.query_grid = QueryGrid { grid, entries };
+    }
+
+    fn collider(&self, kind: EntityKind, id: Id) -> Option<&Collider> {
+        match kind {
+            EntityKind::Doodle => get!(self.doodles, id, (&body.collider)).map(|(c,)| c),
+            EntityKind::Cloud => get!(self.clouds, id, (&body.collider)).map(|(c,)| c),
+            EntityKind::Bird => get!(self.birds, id, (&body.collider)).map(|(c,)| c),
+            EntityKind::Projectile => get!(self.projectiles, id, (&body.collider)).map(|(c,)| c),
+            EntityKind::Trigger => get!(self.triggers, id, (&collider)).map(|(c,)| c),
+        }
+    }
+
+    /// All the colliders that queries can hit.
+    fn colliders(&self) -> impl Iterator<Item = (EntityKind, Id, &Collider)> + '_ {
+        let doodles = query!(self.doodles, (&body.collider))
+            .map(|(id, (collider,))| (EntityKind::Doodle, id, collider));
+        let clouds = query!(self.clouds, (&body.collider))
+            .map(|(id, (collider,))| (EntityKind::Cloud, id, collider));
+        let birds = query!(self.birds, (&body.collider))
+            .map(|(id, (collider,))| (EntityKind::Bird, id, collider));
+        let projectiles = query!(self.projectiles, (&body.collider))
+            .map(|(id, (collider,))| (EntityKind::Projectile, id, collider));
+        let triggers = query!(self.triggers, (&collider))
+            .map(|(id, (collider,))| (EntityKind::Trigger, id, collider));
+        doodles
+            .chain(clouds)
+            .chain(birds)
+            .chain(projectiles)
+            .chain(triggers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(model: &Model, x: f32, y: f32) -> Position {
+        Position::from_world(vec2(x, y).as_r32(), model.world_width)
+    }
+
+    fn bird(model: &mut Model, x: f32, y: f32) -> Id {
+        let pos = position(model, x, y);
+        model.birds.insert(Bird::new(pos, 0.0))
+    }
+
+    const BIRDS: &[EntityKind] = &[EntityKind::Bird];
+
+    #[test]
+    fn raycast_finds_cloud_across_seam() {
+        let mut model = logic::test_model(0);
+        let width = model.world_width;
+        let cloud = model.clouds.insert(Cloud::new(Position::zero(width)));
+        model.update_query_grid();
+
+        let origin = position(&model, -0.1, 0.5);
+        let hit = model
+            .raycast(
+                origin,
+                vec2(0.0, -1.0).as_r32(),
+                r32(1.0),
+                QueryFilter::only(&[EntityKind::Cloud]),
+            )
+            .expect("the ray should hit the cloud");
+        assert_eq!((hit.kind, hit.id), (EntityKind::Cloud, cloud));
+        assert!(hit.distance < r32(0.5));
+    }
+
+    #[test]
+    fn shape_cast_hits_the_closest_bird() {
+        let mut model = logic::test_model(0);
+        let near = bird(&mut model, 3.0, 10.0);
+        let _far = bird(&mut model, 6.0, 10.0);
+        model.update_query_grid();
+
+        let hit = model
+            .shape_cast(
+                &Shape::circle(0.2),
+                position(&model, 0.0, 10.0),
+                vec2(1.0, 0.0).as_r32(),
+                r32(10.0),
+                QueryFilter::only(BIRDS),
+            )
+            .expect("the cast should hit a bird");
+        assert_eq!(hit.id, near);
+        // The bird's radius is 0.5, and the cast shape's 0.2
+        assert!((hit.distance - r32(2.3)).abs() < r32(0.01));
+    }
+
+    #[test]
+    fn shape_cast_wraps_around_the_seam() {
+        let mut model = logic::test_model(0);
+        let width = model.world_width.as_f32();
+        let target = bird(&mut model, 1.0, 10.0);
+        model.update_query_grid();
+
+        let hit = model
+            .shape_cast(
+                &Shape::circle(0.2),
+                position(&model, width - 1.0, 10.0),
+                vec2(1.0, 0.0).as_r32(),
+                r32(5.0),
+                QueryFilter::only(BIRDS),
+            )
+            .expect("the cast should hit the bird across the seam");
+        assert_eq!(hit.id, target);
+        assert!((hit.distance - r32(1.3)).abs() < r32(0.01));
+    }
+
+    #[test]
+    fn excluded_entity_is_skipped() {
+        let mut model = logic::test_model(0);
+        let shooter = bird(&mut model, 0.0, 10.0);
+        let target = bird(&mut model, 3.0, 10.0);
+        model.update_query_grid();
+
+        let origin = position(&model, 0.0, 10.0);
+        let direction = vec2(1.0, 0.0).as_r32();
+        let hit = model
+            .raycast(origin, direction, r32(10.0), QueryFilter::only(BIRDS))
+            .unwrap();
+        assert_eq!(hit.id, shooter);
+
+        let filter = QueryFilter::only(BIRDS).excluding(EntityKind::Bird, shooter);
+        let hit = model.raycast(origin, direction, r32(10.0), filter).unwrap();
+        assert_eq!(hit.id, target);
+    }
+
+    #[test]
+    fn ray_passes_one_way_segment_from_below() {
+        let mut model = logic::test_model(0);
+        let mut cloud = Cloud::new(position(&model, 0.0, 10.0));
+        cloud.body.collider.set_shape(Shape::Segment {
+            a: vec2(-1.0, 0.0).as_r32(),
+            b: vec2(1.0, 0.0).as_r32(),
+            one_way: true,
+        });
+        let cloud = model.clouds.insert(cloud);
+        model.update_query_grid();
+        let filter = QueryFilter::only(&[EntityKind::Cloud]);
+
+        let from_below = model.raycast(
+            position(&model, 0.0, 9.0),
+            vec2(0.0, 1.0).as_r32(),
+            r32(2.0),
+            filter,
+        );
+        assert!(from_below.is_none());
+
+        let from_above = model
+            .raycast(
+                position(&model, 0.0, 11.0),
+                vec2(0.0, -1.0).as_r32(),
+                r32(2.0),
+                filter,
+            )
+            .expect("the platform is solid from above");
+        assert_eq!(from_above.id, cloud);
+        assert!((from_above.distance - r32(1.0)).abs() < r32(0.01));
+    }
+}
@@ -0,0 +1,396 @@
+use super::*;
+
+use rand_chacha::ChaCha8Rng;
+use std::{collections::HashMap, path::Path};
+
+/// The whole state of the world, that can be saved and restored.
+///
+/// Entities are stored as lists, and references between them
+/// are stored as indices into those lists.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub config: Config,
+    pub chunks: Vec<Chunk>,
+    pub seed: u64,
+    pub rng: ChaCha8Rng,
+    pub particle_rng: ChaCha8Rng,
+    pub time: Time,
+    pub world_width: Coord,
+    pub generated_height: Coord,
+    pub last_generated_cloud: Option<usize>,
+    pub scripted_birds: Vec<ScriptedBird>,
+    pub next_bird: Time,
+    pub camera: Camera,
+    pub players: Vec<SavedPlayer>,
+    pub doodles: Vec<SavedDoodle>,
+    pub birds: Vec<Bird>,
+    pub clouds: Vec<Cloud>,
+    pub projectiles: Vec<Projectile>,
+    pub triggers: Vec<SavedTrigger>,
+    pub particles: Vec<Particle>,
+    pub shhh_playing: bool,
+    pub shhh_volume: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedPlayer {
+    /// Index of the doodle, `None` if eliminated.
+    pub body: Option<usize>,
+    pub score: i32,
+    pub stats: Stats,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedDoodle {
+    pub body: Body,
+    /// Index of the cloud.
+    pub grounded: Option<usize>,
+    /// Indices of the triggers.
+    pub active_triggers: Vec<usize>,
+    pub coyote_time: Time,
+    pub shoot_cooldown: Time,
+    pub power_ups: PowerUps,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedTrigger {
+    pub kind: TriggerKind,
+    pub collider: Collider,
+    pub attached_to: Option<SavedAttachment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedAttachment {
+    pub relative_pos: vec2<Coord>,
+    /// Index of the cloud.
+    pub cloud: usize,
+}
+
+impl Snapshot {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("failed to open snapshot at {path:?}"))?;
+        let snapshot: Self = bincode::deserialize_from(std::io::BufReader::new(file))
+            .with_context(|| format!("failed to parse snapshot at {path:?}"))?;
+        snapshot
+            .validate()
+            .with_context(|| format!("snapshot at {path:?} is corrupted"))?;
+        Ok(snapshot)
+    }
+
+    /// Check that the config and all shapes are valid and all references
+    /// between entities point to existing entities, so that the snapshot
+    /// can be restored.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.config.validate().context("invalid config")?;
+        validate_chunks(&self.chunks)?;
+        anyhow::ensure!(!self.players.is_empty(), "no players");
+
+        let check_shape = |collider: &Collider, what: &str, i: usize| {
+            collider
+                .shape()
+                .validate()
+                .with_context(|| format!("invalid shape of {what} {i}"))
+        };
+        for (i, doodle) in self.doodles.iter().enumerate() {
+            check_shape(&doodle.body.collider, "doodle", i)?;
+        }
+        for (i, bird) in self.birds.iter().enumerate() {
+            check_shape(&bird.body.collider, "bird", i)?;
+        }
+        for (i, cloud) in self.clouds.iter().enumerate() {
+            check_shape(&cloud.body.collider, "cloud", i)?;
+        }
+        for (i, projectile) in self.projectiles.iter().enumerate() {
+            check_shape(&projectile.body.collider, "projectile", i)?;
+        }
+        for (i, trigger) in self.triggers.iter().enumerate() {
+            check_shape(&trigger.collider, "trigger", i)?;
+        }
+        for (i, particle) in self.particles.iter().enumerate() {
+            check_shape(&particle.body.collider, "particle", i)?;
+        }
+
+        let check = |index: usize, len: usize, what: &str| -> anyhow::Result<()> {
+            anyhow::ensure!(
+                index < len,
+                "reference to {what} {index}, but there are only {len}"
+            );
+            Ok(())
+        };
+        let clouds = self.clouds.len();
+        if let Some(cloud) = self.last_generated_cloud {
+            check(cloud, clouds, "cloud")?;
+        }
+        for player in &self.players {
+            if let Some(doodle) = player.body {
+                check(doodle, self.doodles.len(), "doodle")?;
+            }
+        }
+        for doodle in &self.doodles {
+            if let Some(cloud) = doodle.grounded {
+                check(cloud, clouds, "cloud")?;
+            }
+            for &trigger in &doodle.active_triggers {
+                check(trigger, self.triggers.len(), "trigger")?;
+            }
+        }
+        for trigger in &self.triggers {
+            if let Some(attachment) = &trigger.attached_to {
+                check(attachment.cloud, clouds, "cloud")?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("failed to create snapshot at {path:?}"))?;
+        bincode::serialize_into(std::io::BufWriter::new(file), self)
+            .with_context(|| format!("failed to write snapshot to {path:?}"))?;
+        Ok(())
+    }
+}
+
+impl Model {
+    pub fn to_snapshot(&self) -> Snapshot {
+        let mut doodles = self.doodles.clone();
+        let doodle_ids = doodles.ids();
+        let mut birds = self.birds.clone();
+        let bird_ids = birds.ids();
+        let mut clouds = self.clouds.clone();
+        let cloud_ids = clouds.ids();
+        let mut projectiles = self.projectiles.clone();
+        let projectile_ids = projectiles.ids();
+        let mut triggers = self.triggers.clone();
+        let trigger_ids = triggers.ids();
+        let mut particles = self.particles.clone();
+        let particle_ids = particles.ids();
+
+        let index_of = |ids: &[Id]| -> HashMap<Id, usize> {
+            ids.iter().enumerate().map(|(i, &id)| (id, i)).collect()
+        };
+        let doodle_index = index_of(&doodle_ids);
+        let cloud_index = index_of(&cloud_ids);
+        let trigger_index = index_of(&trigger_ids);
+
+        Snapshot {
+            config: self.config.clone(),
+            chunks: self.chunks.clone(),
+            seed: self.seed,
+            rng: self.rng.clone(),
+            particle_rng: self.particle_rng.clone(),
+            time: self.time,
+            world_width: self.world_width,
+            generated_height: self.generated_height,
+            last_generated_cloud: self
+                .last_generated_cloud
+                .and_then(|id| cloud_index.get(&id).copied()),
+            scripted_birds: self.scripted_birds.clone(),
+            next_bird: self.next_bird,
+            camera: self.camera.clone(),
+            players: self
+                .players
+                .iter()
+                .map(|player| SavedPlayer {
+                    body: player.body.map(|id| doodle_index[&id]),
+                    score: player.score,
+                    stats: player.stats.clone(),
+                })
+                .collect(),
+            doodles: doodle_ids
+                .iter()
+                .map(|&id| {
+                    let doodle = doodles.remove(id).unwrap();
+                    SavedDoodle {
+                        body: doodle.body,
+                        grounded: doodle.grounded.and_then(|id| cloud_index.get(&id).copied()),
+                        active_triggers: doodle
+                            .active_triggers
+                            .iter()
+                            .filter_map(|id| trigger_index.get(id).copied())
+                            .collect(),
+                        coyote_time: doodle.coyote_time,
+                        shoot_cooldown: doodle.shoot_cooldown,
+                        power_ups: doodle.power_ups,
+                    }
+                })
+                .collect(),
+            birds: bird_ids
+                .iter()
+                .map(|&id| birds.remove(id).unwrap())
+                .collect(),
+            clouds: cloud_ids
+                .iter()
+                .map(|&id| clouds.remove(id).unwrap())
+                .collect(),
+            projectiles: projectile_ids
+                .iter()
+                .map(|&id| projectiles.remove(id).unwrap())
+                .collect(),
+            triggers: trigger_ids
+                .iter()
+                .map(|&id| {
+                    let trigger = triggers.remove(id).unwrap();
+                    SavedTrigger {
+                        kind: trigger.kind,
+                        collider: trigger.collider,
+                        attached_to: trigger.attached_to.and_then(|attachment| {
+                            Some(SavedAttachment {
+                                relative_pos: attachment.relative_pos,
+                                cloud: *cloud_index.get(&attachment.cloud)?,
+                            })
+                        }),
+                    }
+                })
+                .collect(),
+            particles: particle_ids
+                .iter()
+                .map(|&id| particles.remove(id).unwrap())
+                .collect(),
+            shhh_playing: self.shhh_playing,
+            shhh_volume: self.shhh_volume,
+        }
+    }
+
+    /// Restore the world from a snapshot.
+    ///
+    /// Panics if the snapshot does not pass [`Snapshot::validate`].
+    pub fn from_snapshot(snapshot: Snapshot) -> Self {
+        let mut clouds: StructOf<Arena<Cloud>> = default();
+        let cloud_ids: Vec<Id> = snapshot
+            .clouds
+            .into_iter()
+            .map(|cloud| clouds.insert(cloud))
+            .collect();
+
+        let mut triggers: StructOf<Arena<Trigger>> = default();
+        let trigger_ids: Vec<Id> = snapshot
+            .triggers
+            .into_iter()
+            .map(|trigger| {
+                triggers.insert(Trigger {
+                    kind: trigger.kind,
+                    collider: trigger.collider,
+                    attached_to: trigger.attached_to.map(|attachment| Attachment {
+                        relative_pos: attachment.relative_pos,
+                        cloud: cloud_ids[attachment.cloud],
+                    }),
+                })
+            })
+            .collect();
+
+        let mut doodles: StructOf<Arena<Doodle>> = default();
+        let doodle_ids: Vec<Id> = snapshot
+            .doodles
+            .into_iter()
+            .map(|doodle| {
+                doodles.insert(Doodle {
+                    body: doodle.body,
+                    grounded: doodle.grounded.map(|i| cloud_ids[i]),
+                    active_triggers: doodle
+                        .active_triggers
+                        .into_iter()
+                        .map(|i| trigger_ids[i])
+                        .collect(),
+                    coyote_time: doodle.coyote_time,
+                    shoot_cooldown: doodle.shoot_cooldown,
+                    power_ups: doodle.power_ups,
+                })
+            })
+            .collect();
+
+        let mut birds: StructOf<Arena<Bird>> = default();
+        for bird in snapshot.birds {
+            birds.insert(bird);
+        }
+        let mut projectiles: StructOf<Arena<Projectile>> = default();
+        for projectile in snapshot.projectiles {
+            projectiles.insert(projectile);
+        }
+        let mut particles: StructOf<Arena<Particle>> = default();
+        for particle in snapshot.particles {
+            particles.insert(particle);
+        }
+
+        let mut model = Self {
+            config: snapshot.config,
+            chunks: snapshot.chunks,
+            seed: snapshot.seed,
+            rng: snapshot.rng,
+            particle_rng: snapshot.particle_rng,
+            time: snapshot.time,
+            world_width: snapshot.world_width,
+            generated_height: snapshot.generated_height,
+            last_generated_cloud: snapshot.last_generated_cloud.map(|i| cloud_ids[i]),
+            scripted_birds: snapshot.scripted_birds,
+            next_bird: snapshot.next_bird,
+            camera: snapshot.camera,
+            players: snapshot
+                .players
+                .into_iter()
+                .map(|player| Player {
+                    body: player.body.map(|i| doodle_ids[i]),
+                    score: player.score,
+                    stats: player.stats,
+                })
+                .collect(),
+            doodles,
+            birds,
+            clouds,
+            projectiles,
+            triggers,
+            particles,
+            shhh_playing: snapshot.shhh_playing,
+            shhh_volume: snapshot.shhh_volume,
+            events: Vec::new(),
+            query_grid: QueryGrid::new(snapshot.world_width),
+        };
+        model.update_query_grid();
+        model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broken_references_are_rejected() {
+        let mut model = logic::test_model(3);
+        model.generate_level(r32(1.0 / 120.0));
+        let snapshot = model.to_snapshot();
+        snapshot.validate().unwrap();
+
+        let mut broken = snapshot.clone();
+        broken.last_generated_cloud = Some(broken.clouds.len());
+        assert!(broken.validate().is_err());
+
+        let mut broken = snapshot;
+        broken.players[0].body = Some(broken.doodles.len());
+        assert!(broken.validate().is_err());
+    }
+
+    #[test]
+    fn invalid_shapes_are_rejected() {
+        let mut model = logic::test_model(1);
+        model.generate_level(r32(1.0 / 120.0));
+        let snapshot = model.to_snapshot();
+
+        let mut broken = snapshot.clone();
+        broken.clouds[0]
+            .body
+            .collider
+            .set_shape(Shape::rectangle(0.0, 1.0));
+        assert!(broken.validate().is_err());
+
+        let mut broken = snapshot;
+        broken.doodles[0]
+            .body
+            .collider
+            .set_shape(Shape::circle(-1.0));
+        assert!(broken.validate().is_err());
+    }
+}
@@ -0,0 +1,18 @@
+use super::*;
+
+/// Statistics of the current run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stats {
+    /// The highest point the player has reached.
+    pub max_height: Coord,
+    pub coins_collected: u32,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            max_height: Coord::ZERO,
+            coins_collected: 0,
+        }
+    }
+}
@@ -0,0 +1,494 @@
+mod protocol;
+/// Run by the server binary, compiled here to test the client against it.
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod relay;
+
+pub use protocol::*;
+
+use crate::{
+    menu::{menu_camera, MainMenu, BACKGROUND_COLOR, SELECTED_COLOR, TEXT_COLOR},
+    prelude::*,
+};
+
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    net::{TcpStream, ToSocketAddrs},
+    sync::mpsc,
+};
+
+/// Connection to the versus server.
+/// Messages are sent and received on separate threads,
+/// so the game never waits for the network.
+pub struct Connection {
+    #[cfg(not(target_arch = "wasm32"))]
+    sender: mpsc::Sender<ClientMessage>,
+    #[cfg(not(target_arch = "wasm32"))]
+    receiver: mpsc::Receiver<ServerMessage>,
+    connected: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Connection {
+    pub fn connect(addr: impl ToSocketAddrs) -> anyhow::Result<Self> {
+        let mut stream = TcpStream::connect(addr).context("failed to connect to the server")?;
+        stream.set_nodelay(true)?;
+
+        let mut reader = std::io::BufReader::new(stream.try_clone()?);
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            while let Ok(message) = receive(&mut reader) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let (sender, outgoing) = mpsc::channel::<ClientMessage>();
+        std::thread::spawn(move || {
+            for message in outgoing {
+                if let Err(err) = send(&mut stream, &message) {
+                    log::error!("Lost connection to the server: {err:?}");
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            sender,
+            receiver,
+            connected: true,
+        })
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) {
+        if !self.connected {
+            return;
+        }
+        // Fails only if the writing thread has stopped because of an error
+        if self.sender.send(message.clone()).is_err() {
+            self.connected = false;
+        }
+    }
+
+    /// Returns all messages received since the last call.
+    pub fn poll(&mut self) -> Vec<ServerMessage> {
+        let mut messages = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(message) => messages.push(message),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.connected = false;
+                    break;
+                }
+            }
+        }
+        messages
+    }
+}
+
+/// There are no sockets on the web, so the connection always fails.
+#[cfg(target_arch = "wasm32")]
+impl Connection {
+    pub fn connect(_addr: &str) -> anyhow::Result<Self> {
+        anyhow::bail!("online races are not supported on the web")
+    }
+
+    pub fn send(&mut self, _message: &ClientMessage) {}
+
+    pub fn poll(&mut self) -> Vec<ServerMessage> {
+        Vec::new()
+    }
+}
+
+impl Connection {
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+}
+
+/// The last known state of another player.
+#[derive(Debug, Clone, Copy)]
+pub struct Ghost {
+    pub position: vec2<f32>,
+    pub score: i32,
+    pub finished: bool,
+}
+
+/// A race against other players over the network.
+/// Every client simulates its own run on the same seed
+/// and only sees the others as ghosts.
+pub struct OnlineRace {
+    connection: Connection,
+    /// Index of the local player.
+    pub player: usize,
+    pub ghosts: HashMap<usize, Ghost>,
+    pub standings: Option<Vec<Standing>>,
+}
+
+impl OnlineRace {
+    pub fn new(connection: Connection, player: usize) -> Self {
+        Self {
+            connection,
+            player,
+            ghosts: HashMap::new(),
+            standings: None,
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_connected()
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) {
+        self.connection.send(message);
+    }
+
+    /// Process the messages from the server.
+    pub fn update(&mut self) {
+        for message in self.connection.poll() {
+            match message {
+                ServerMessage::Ghost {
+                    player,
+                    position,
+                    score,
+                } => {
+                    self.ghosts.insert(
+                        player,
+                        Ghost {
+                            position,
+                            score,
+                            finished: false,
+                        },
+                    );
+                }
+                ServerMessage::Finished { player, score } => {
+                    let ghost = self.ghosts.entry(player).or_insert(Ghost {
+                        position: vec2::ZERO,
+                        score,
+                        finished: true,
+                    });
+                    ghost.score = score;
+                    ghost.finished = true;
+                }
+                ServerMessage::Standings(standings) => self.standings = Some(standings),
+                ServerMessage::Start { .. } => {
+                    log::error!("Unexpected start message during the race");
+                }
+            }
+        }
+    }
+}
+
+/// Waits for the other players to connect before starting the race.
+pub struct Lobby {
+    geng: Geng,
+    assets: Rc<Assets>,
+    transition: Option<geng::state::Transition>,
+    connection: anyhow::Result<Connection>,
+}
+
+impl Lobby {
+    pub fn new(geng: &Geng, assets: &Rc<Assets>, addr: &str) -> Self {
+        let connection = Connection::connect(addr);
+        if let Err(err) = &connection {
+            log::error!("{err:?}");
+        }
+        Self {
+            geng: geng.clone(),
+            assets: assets.clone(),
+            transition: None,
+            connection,
+        }
+    }
+}
+
+impl geng::State for Lobby {
+    fn update(&mut self, _delta_time: f64) {
+        let Ok(connection) = &mut self.connection else {
+            return;
+        };
+        let start = connection
+            .poll()
+            .into_iter()
+            .find_map(|message| match message {
+                ServerMessage::Start { seed, player, .. } => Some((seed, player)),
+                _ => None,
+            });
+        let connected = connection.is_connected();
+
+        if let Some((seed, player)) = start {
+            let connection =
+                std::mem::replace(&mut self.connection, Err(anyhow!("the race has started")));
+            if let Ok(connection) = connection {
+                let race = OnlineRace::new(connection, player);
+                self.transition = Some(geng::state::Transition::Switch(Box::new(
+                    crate::game::Game::online(&self.geng, &self.assets, seed, race),
+                )));
+            }
+        } else if !connected {
+            self.connection = Err(anyhow!("the server closed the connection"));
+        }
+    }
+
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        ugli::clear(
+            framebuffer,
+            Some(BACKGROUND_COLOR.try_into().unwrap()),
+            None,
+            None,
+        );
+        let text = match &self.connection {
+            Ok(_) => "Waiting for the other players...".to_string(),
+            Err(err) => format!("Error: {err}"),
+        };
+        self.geng.default_font().draw(
+            framebuffer,
+            &menu_camera(),
+            &text,
+            vec2::splat(geng::TextAlign::CENTER),
+            mat3::scale_uniform(0.6),
+            TEXT_COLOR.try_into().unwrap(),
+        );
+    }
+
+    fn handle_event(&mut self, event: geng::Event) {
+        if geng_utils::key::is_event_press(&event, [geng::Key::Escape]) {
+            self.transition = Some(geng::state::Transition::Switch(Box::new(MainMenu::new(
+                &self.geng,
+                &self.assets,
+            ))));
+        }
+    }
+
+    fn transition(&mut self) -> Option<geng::state::Transition> {
+        self.transition.take()
+    }
+}
+
+/// Shown after the local player has been eliminated,
+/// until everyone has finished.
+pub struct ResultsScreen {
+    geng: Geng,
+    assets: Rc<Assets>,
+    transition: Option<geng::state::Transition>,
+    race: OnlineRace,
+    score: i32,
+}
+
+impl ResultsScreen {
+    pub fn new(geng: &Geng, assets: &Rc<Assets>, mut race: OnlineRace, score: i32) -> Self {
+        race.send(&ClientMessage::Finished { score });
+        Self {
+            geng: geng.clone(),
+            assets: assets.clone(),
+            transition: None,
+            race,
+            score,
+        }
+    }
+}
+
+impl geng::State for ResultsScreen {
+    fn update(&mut self, _delta_time: f64) {
+        self.race.update();
+    }
+
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        let camera = menu_camera();
+        ugli::clear(
+            framebuffer,
+            Some(BACKGROUND_COLOR.try_into().unwrap()),
+            None,
+            None,
+        );
+
+        let lines: Vec<(String, bool)> = match &self.race.standings {
+            Some(standings) => std::iter::once(("Results".to_string(), false))
+                .chain(standings.iter().enumerate().map(|(place, standing)| {
+                    let you = standing.player == self.race.player;
+                    let name = if you {
+                        "You".to_string()
+                    } else {
+                        format!("Player {}", standing.player + 1)
+                    };
+                    (format!("{}. {}  {}", place + 1, name, standing.score), you)
+                }))
+                .collect(),
+            None => {
+                let status = if self.race.is_connected() {
+                    "Waiting for the others to finish..."
+                } else {
+                    "Lost connection to the server"
+                };
+                vec![
+                    (format!("Your score: {}", self.score), true),
+                    (status.to_string(), false),
+                ]
+            }
+        };
+        for (i, (text, highlight)) in lines.iter().enumerate() {
+            let color = if *highlight {
+                SELECTED_COLOR
+            } else {
+                TEXT_COLOR
+            };
+            self.geng.default_font().draw(
+                framebuffer,
+                &camera,
+                text,
+                vec2::splat(geng::TextAlign::CENTER),
+                mat3::translate(vec2(0.0, 3.0 - i as f32 * 1.2)) * mat3::scale_uniform(0.7),
+                color.try_into().unwrap(),
+            );
+        }
+    }
+
+    fn handle_event(&mut self, event: geng::Event) {
+        if geng_utils::key::is_event_press(&event, [geng::Key::Escape, geng::Key::Enter]) {
+            self.transition = Some(geng::state::Transition::Switch(Box::new(MainMenu::new(
+                &self.geng,
+                &self.assets,
+            ))));
+        }
+    }
+
+    fn transition(&mut self) -> Option<geng::state::Transition> {
+        self.transition.take()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use std::{
+        net::TcpListener,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn round_trip_over_localhost() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let message: ClientMessage = receive(&mut stream).unwrap();
+            let ClientMessage::Finished { score } = message else {
+                panic!("unexpected message {message:?}");
+            };
+            send(&mut stream, &ServerMessage::Finished { player: 1, score }).unwrap();
+        });
+
+        let mut connection = Connection::connect(addr).unwrap();
+        connection.send(&ClientMessage::Finished { score: 42 });
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let reply = loop {
+            if let Some(message) = connection.poll().into_iter().next() {
+                break message;
+            }
+            assert!(Instant::now() < deadline, "no reply from the server");
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert!(matches!(
+            reply,
+            ServerMessage::Finished {
+                player: 1,
+                score: 42
+            }
+        ));
+        server.join().unwrap();
+    }
+
+    /// Poll the connection until `count` messages have arrived.
+    fn wait_for(connection: &mut Connection, count: usize) -> Vec<ServerMessage> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut messages = Vec::new();
+        while messages.len() < count {
+            messages.extend(connection.poll());
+            assert!(Instant::now() < deadline, "only got {messages:?}");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(messages.len(), count, "{messages:?}");
+        messages
+    }
+
+    #[test]
+    fn race_is_relayed_between_two_clients() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let streams = listener
+                .incoming()
+                .take(2)
+                .map(|stream| stream.unwrap())
+                .collect();
+            relay::run_race(streams)
+        });
+
+        let mut clients = [
+            Connection::connect(addr).unwrap(),
+            Connection::connect(addr).unwrap(),
+        ];
+        let mut seeds = Vec::new();
+        let mut players = Vec::new();
+        for client in &mut clients {
+            let [ServerMessage::Start {
+                seed,
+                player,
+                players: 2,
+            }] = wait_for(client, 1)[..]
+            else {
+                panic!("the race did not start");
+            };
+            seeds.push(seed);
+            players.push(player);
+        }
+        // Everyone races on the same level
+        assert_eq!(seeds[0], seeds[1]);
+        assert_ne!(players[0], players[1]);
+        let [a, b] = &mut clients;
+
+        a.send(&ClientMessage::State {
+            position: vec2(1.0, 2.0),
+            score: 10,
+        });
+        let ghost = wait_for(b, 1);
+        assert!(
+            matches!(
+                ghost[..],
+                [ServerMessage::Ghost { player, position, score: 10 }]
+                    if player == players[0] && position == vec2(1.0, 2.0)
+            ),
+            "{ghost:?}"
+        );
+
+        b.send(&ClientMessage::Finished { score: 5 });
+        let finished = wait_for(a, 1);
+        assert!(
+            matches!(
+                finished[..],
+                [ServerMessage::Finished { player, score: 5 }] if player == players[1]
+            ),
+            "{finished:?}"
+        );
+
+        // The last one to finish ends the race
+        a.send(&ClientMessage::Finished { score: 20 });
+        let messages = wait_for(b, 2);
+        let [ServerMessage::Finished { .. }, ServerMessage::Standings(standings)] = &messages[..]
+        else {
+            panic!("no standings");
+        };
+        let standings: Vec<(usize, i32)> = standings
+            .iter()
+            .map(|standing| (standing.player, standing.score))
+            .collect();
+        assert_eq!(standings, [(players[0], 20), (players[1], 5)]);
+        assert!(matches!(wait_for(a, 1)[..], [ServerMessage::Standings(_)]));
+
+        server.join().unwrap().unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+//! Messages exchanged between the game and the versus server.
+//! Shared with the server binary, so it only depends on `geng`.
+
+use geng::prelude::*;
+
+pub const DEFAULT_ADDR: &str = "127.0.0.1:4000";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// The current state of the player's race, sent every frame.
+    State {
+        /// Position of the doodle in world coordinates.
+        position: vec2<f32>,
+        score: i32,
+    },
+    /// The player has been eliminated with the final score.
+    Finished { score: i32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ServerMessage {
+    /// Everyone has joined, the race begins.
+    Start {
+        seed: u64,
+        /// Index of the receiving player.
+        player: usize,
+        players: usize,
+    },
+    /// The state of another player's race.
+    Ghost {
+        player: usize,
+        position: vec2<f32>,
+        score: i32,
+    },
+    /// Another player has been eliminated.
+    Finished { player: usize, score: i32 },
+    /// Everyone has finished, the final scores sorted from best to worst.
+    Standings(Vec<Standing>),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Standing {
+    pub player: usize,
+    pub score: i32,
+}
+
+/// Write a message to the stream.
+pub fn send<T: Serialize>(stream: &mut impl std::io::Write, message: &T) -> anyhow::Result<()> {
+    // Serialize first, so the message is sent in a single write
+    let data = bincode::serialize(message)?;
+    stream.write_all(&data)?;
+    Ok(())
+}
+
+/// Read the next message from the stream, blocking until it arrives.
+pub fn receive<T: serde::de::DeserializeOwned>(
+    stream: &mut impl std::io::Read,
+) -> anyhow::Result<T> {
+    Ok(bincode::deserialize_from(stream)?)
+}
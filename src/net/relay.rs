@@ -0,0 +1,97 @@
+//! The race logic of the versus server.
+//! Shared with the server binary, so it only depends on `geng` and the protocol.
+
+use super::protocol::*;
+
+use geng::prelude::*;
+
+use std::{io::BufReader, net::TcpStream, sync::mpsc};
+
+/// A message from a player, or `None` if they disconnected.
+type Incoming = (usize, Option<ClientMessage>);
+
+/// Relay the messages between the players until everyone has finished,
+/// then send them the final standings.
+pub fn run_race(mut streams: Vec<TcpStream>) -> anyhow::Result<()> {
+    let players = streams.len();
+    let seed: u64 = thread_rng().gen();
+    log::info!("Starting a race of {players} players with seed {seed}");
+
+    let (sender, receiver) = mpsc::channel::<Incoming>();
+    for (player, stream) in streams.iter_mut().enumerate() {
+        send(
+            stream,
+            &ServerMessage::Start {
+                seed,
+                player,
+                players,
+            },
+        )?;
+
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let sender = sender.clone();
+        std::thread::spawn(move || {
+            while let Ok(message) = receive(&mut reader) {
+                if sender.send((player, Some(message))).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send((player, None));
+        });
+    }
+    drop(sender);
+
+    let mut scores = vec![0; players];
+    let mut finished = vec![false; players];
+    while finished.contains(&false) {
+        let Ok((player, message)) = receiver.recv() else {
+            break;
+        };
+        let relay = match message {
+            Some(ClientMessage::State { position, score }) => {
+                scores[player] = score;
+                ServerMessage::Ghost {
+                    player,
+                    position,
+                    score,
+                }
+            }
+            Some(ClientMessage::Finished { score }) => {
+                scores[player] = score;
+                finished[player] = true;
+                ServerMessage::Finished { player, score }
+            }
+            None => {
+                // Count a disconnected player as finished with their last score
+                log::info!("Player {player} disconnected");
+                if finished[player] {
+                    continue;
+                }
+                finished[player] = true;
+                ServerMessage::Finished {
+                    player,
+                    score: scores[player],
+                }
+            }
+        };
+        for (other, stream) in streams.iter_mut().enumerate() {
+            if other != player {
+                // The player might have disconnected, which is handled by their reader
+                let _ = send(stream, &relay);
+            }
+        }
+    }
+
+    let mut standings: Vec<Standing> = scores
+        .iter()
+        .enumerate()
+        .map(|(player, &score)| Standing { player, score })
+        .collect();
+    standings.sort_by_key(|standing| std::cmp::Reverse(standing.score));
+    log::info!("Race over: {standings:?}");
+    let message = ServerMessage::Standings(standings);
+    for stream in &mut streams {
+        let _ = send(stream, &message);
+    }
+    Ok(())
+}
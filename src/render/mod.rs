@@ -1,63 +1,100 @@
-use crate::prelude::*;
+use crate::{net::Ghost, prelude::*};
 
 pub struct GameRender {
     geng: Geng,
     assets: Rc<Assets>,
     backgrounds: [usize; 3],
     background_scroll: usize,
+    /// Used to select background tiling, seeded from the model's seed.
+    rng: StdRng,
 }
 
 impl GameRender {
-    pub fn new(geng: &Geng, assets: &Rc<Assets>) -> Self {
+    pub fn new(geng: &Geng, assets: &Rc<Assets>, seed: u64) -> Self {
         Self {
             geng: geng.clone(),
             assets: assets.clone(),
             backgrounds: [0, 1, 0],
             background_scroll: 0,
+            rng: StdRng::seed_from_u64(seed),
         }
     }
 
-    pub fn draw(&mut self, model: &Model, framebuffer: &mut ugli::Framebuffer) {
-        self.draw_background(model, framebuffer);
+    /// Draw the model, interpolating positions by `alpha`
+    /// between the previous and the current simulation tick.
+    pub fn draw(&mut self, model: &Model, alpha: f32, framebuffer: &mut ugli::Framebuffer) {
+        let alpha = r32(alpha);
+        let camera = &model.camera.interpolated(alpha);
+
+        self.draw_background(model, camera, framebuffer);
 
         for (_, (collider, kind)) in query!(model.triggers, (&collider, &kind)) {
             let texture = match kind {
                 TriggerKind::Spring => &self.assets.sprites.spring,
                 TriggerKind::Coin => &self.assets.sprites.coin,
+                TriggerKind::PowerUp(power_up) => {
+                    self.draw_collider_transformed(
+                        &collider.clone().interpolated(alpha),
+                        power_up.color(),
+                        mat3::identity(),
+                        camera,
+                        framebuffer,
+                    );
+                    continue;
+                }
             };
-            self.draw_sprite(&collider.clone(), texture, &model.camera, framebuffer);
+            self.draw_sprite(
+                &collider.clone().interpolated(alpha),
+                texture,
+                camera,
+                framebuffer,
+            );
         }
         for (_, (collider,)) in query!(model.clouds, (&body.collider)) {
             self.draw_sprite(
-                &collider.clone(),
+                &collider.clone().interpolated(alpha),
                 &self.assets.sprites.cloud,
-                &model.camera,
+                camera,
                 framebuffer,
             );
         }
-        for (_, (collider,)) in query!(model.doodles, (&body.collider)) {
+        for (_, (collider, power_ups)) in query!(model.doodles, (&body.collider, &power_ups)) {
+            let collider = collider.clone().interpolated(alpha);
             self.draw_animation(
-                &collider.clone(),
+                &collider,
                 &self.assets.sprites.doodle,
+                Color::WHITE,
                 model.time,
-                &model.camera,
+                camera,
                 framebuffer,
             );
+            if power_ups.is_active(PowerUp::Shield) {
+                let mut color = PowerUp::Shield.color();
+                color.a = 0.3;
+                self.draw_collider_transformed(
+                    &Collider::new(collider.position, Shape::circle(0.8)),
+                    color,
+                    mat3::identity(),
+                    camera,
+                    framebuffer,
+                );
+            }
         }
-        for (_, (collider,)) in query!(model.birds, (&body.collider)) {
+        for (_, (collider, kind)) in query!(model.birds, (&body.collider, &kind)) {
             self.draw_animation(
-                &collider.clone(),
+                &collider.clone().interpolated(alpha),
                 &self.assets.sprites.bird,
+                kind.color(),
                 model.time,
-                &model.camera,
+                camera,
                 framebuffer,
             );
         }
         for (_, (collider,)) in query!(model.projectiles, (&body.collider)) {
             self.draw_sprite(
-                &collider.clone(),
+                &collider.clone().interpolated(alpha),
                 &self.assets.sprites.bullet,
-                &model.camera,
+                camera,
                 framebuffer,
             );
         }
@@ -73,32 +110,145 @@ impl GameRender {
             let scale = t;
 
             self.draw_collider_transformed(
-                &collider.clone(),
+                &collider.clone().interpolated(alpha),
                 color,
                 mat3::scale_uniform(scale),
-                &model.camera,
+                camera,
+                framebuffer,
+            );
+        }
+
+        if model.players.len() == 1 {
+            self.draw_score("Score", model.players[0].total_score(), 0, framebuffer);
+            self.draw_power_ups(model, 1, framebuffer);
+            return;
+        }
+        for (i, player) in model.players.iter().enumerate() {
+            let name = format!("P{}", i + 1);
+            let label = if player.is_alive() {
+                name.clone()
+            } else {
+                format!("{name} (out)")
+            };
+            self.draw_score(&label, player.total_score(), i, framebuffer);
+
+            // Label the doodle
+            if let Some(id) = player.body {
+                let (collider,) = get!(model.doodles, id, (&body.collider)).unwrap();
+                let pos = camera.project_f32(collider.clone().interpolated(alpha).position);
+                self.geng.default_font().draw(
+                    framebuffer,
+                    camera,
+                    &name,
+                    vec2::splat(geng::TextAlign::CENTER),
+                    mat3::translate(pos + vec2(0.0, 1.0)) * mat3::scale_uniform(0.5),
+                    Color::BLACK,
+                );
+            }
+        }
+        self.draw_power_ups(model, model.players.len(), framebuffer);
+    }
+
+    /// Draw the other players of an online race as translucent doodles.
+    pub fn draw_ghosts(
+        &self,
+        model: &Model,
+        alpha: f32,
+        ghosts: &[&Ghost],
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        let camera = &model.camera.interpolated(r32(alpha));
+        let texture = &self.assets.sprites.doodle[0].texture;
+        for ghost in ghosts {
+            let position = Position::from_world(ghost.position.as_r32(), model.world_width);
+            let collider = Collider::new(position, Shape::rectangle(1.0, 1.0));
+            let mut color = Color::WHITE;
+            color.a = 0.4;
+            self.draw_sprite_colored(&collider, texture, color, camera, framebuffer);
+
+            self.geng.default_font().draw(
                 framebuffer,
+                camera,
+                &ghost.score.to_string(),
+                vec2::splat(geng::TextAlign::CENTER),
+                mat3::translate(camera.project_f32(position) + vec2(0.0, 1.0))
+                    * mat3::scale_uniform(0.5),
+                Color::BLACK,
             );
         }
+    }
 
+    /// Draw the score in the top left corner, at the `line` from the top.
+    fn draw_score(
+        &self,
+        label: &str,
+        score: i32,
+        line: usize,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        self.draw_hud_line(
+            &format!("{label}: {score}"),
+            line,
+            Color::BLACK,
+            framebuffer,
+        );
+    }
+
+    /// List the active power-ups with the time left, starting from the `line`.
+    fn draw_power_ups(&self, model: &Model, mut line: usize, framebuffer: &mut ugli::Framebuffer) {
+        for (i, player) in model.players.iter().enumerate() {
+            let Some(id) = player.body else {
+                continue;
+            };
+            let (power_ups,) = get!(model.doodles, id, (&power_ups)).unwrap();
+            for active in power_ups.iter() {
+                let name = active.kind.name();
+                let label = if model.players.len() == 1 {
+                    name.to_owned()
+                } else {
+                    format!("P{} {name}", i + 1)
+                };
+                let text = format!("{label}: {:.1}s", active.time_left.as_f32());
+                self.draw_hud_line(&text, line, active.kind.color(), framebuffer);
+                line += 1;
+            }
+        }
+    }
+
+    /// Draw a line of text in the top left corner, at the `line` from the top.
+    fn draw_hud_line(
+        &self,
+        text: &str,
+        line: usize,
+        color: Color,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        let font_size = 50.0;
         self.geng.default_font().draw(
             framebuffer,
             &geng::PixelPerfectCamera,
-            &format!("Score: {}", model.current_score()),
+            text,
             vec2(geng::TextAlign::LEFT, geng::TextAlign::TOP),
-            mat3::translate(vec2(0.02, 0.98) * framebuffer.size().as_f32())
-                * mat3::scale_uniform(50.0)
+            mat3::translate(
+                vec2(0.02, 0.98) * framebuffer.size().as_f32()
+                    - vec2(0.0, font_size * 1.2 * line as f32),
+            ) * mat3::scale_uniform(font_size)
                 * mat3::translate(vec2(0.0, -0.5)),
-            Color::BLACK,
+            color,
         );
     }
 
-    fn draw_background(&mut self, model: &Model, framebuffer: &mut ugli::Framebuffer) {
+    fn draw_background(
+        &mut self,
+        model: &Model,
+        camera: &Camera,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
         let background = &self.assets.sprites.backgrounds[0];
         let mut background_size = background.size().as_f32();
         background_size *= model.world_width.as_f32() / background_size.x;
 
-        let camera_pos = model.camera.center.shifted(model.camera.offset_center);
+        let camera_pos = camera.center.shifted(camera.offset_center);
         // let camera_height = camera_pos.to_world().y;
         // let low = camera_height - model.camera.fov;
         // let high = camera_height + model.camera.fov;
@@ -116,13 +266,13 @@ impl GameRender {
             self.background_scroll = scroll;
             self.backgrounds.rotate_left(1);
             *self.backgrounds.last_mut().unwrap() =
-                thread_rng().gen_range(0..self.assets.sprites.backgrounds.len());
+                self.rng.gen_range(0..self.assets.sprites.backgrounds.len());
         }
 
         let delta = delta_norm.map(f32::fract) * background_size;
         let target = camera_pos.shifted(delta.as_r32());
 
-        let target = model.camera.project_f32(target);
+        let target = camera.project_f32(target);
         let target = Aabb2::point(target).extend_symmetric(background_size / 2.0);
 
         let translations = [
@@ -135,7 +285,7 @@ impl GameRender {
             let target = target.translate(translation);
             self.geng.draw2d().draw2d(
                 framebuffer,
-                &model.camera,
+                camera,
                 &draw2d::TexturedQuad::new(target, &self.assets.sprites.backgrounds[i]),
             );
         }
@@ -145,6 +295,7 @@ impl GameRender {
         &self,
         collider: &Collider,
         animation: &[GifFrame],
+        color: Color,
         mut time: Time,
         camera: &Camera,
         framebuffer: &mut ugli::Framebuffer,
@@ -165,7 +316,7 @@ impl GameRender {
         }
         let frame = &animation[i];
 
-        self.draw_sprite(collider, &frame.texture, camera, framebuffer);
+        self.draw_sprite_colored(collider, &frame.texture, color, camera, framebuffer);
     }
 
     fn draw_sprite(
@@ -175,16 +326,27 @@ impl GameRender {
         camera: &Camera,
         framebuffer: &mut ugli::Framebuffer,
     ) {
-        let pos = camera.project_f32(collider.position);
-        let target = collider.compute_aabb().map(Coord::as_f32);
-        let target = target.translate(pos - target.center());
+        self.draw_sprite_colored(collider, texture, Color::WHITE, camera, framebuffer);
+    }
 
-        let target = geng_utils::layout::fit_aabb_width(texture.size().as_f32(), target, 1.0);
-        self.geng.draw2d().draw2d(
-            framebuffer,
-            camera,
-            &draw2d::TexturedQuad::new(target, texture),
-        );
+    fn draw_sprite_colored(
+        &self,
+        collider: &Collider,
+        texture: &ugli::Texture,
+        color: Color,
+        camera: &Camera,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        let size = collider.compute_aabb().map(Coord::as_f32);
+        for pos in camera.visible_images(collider) {
+            let target = size.translate(pos.as_f32() - size.center());
+            let target = geng_utils::layout::fit_aabb_width(texture.size().as_f32(), target, 1.0);
+            self.geng.draw2d().draw2d(
+                framebuffer,
+                camera,
+                &draw2d::TexturedQuad::colored(target, texture, color),
+            );
+        }
     }
 
     // fn draw_collider(
@@ -205,10 +367,33 @@ impl GameRender {
         camera: &Camera,
         framebuffer: &mut ugli::Framebuffer,
     ) {
-        let pos = camera.project_f32(collider.position);
         let rotation = collider.rotation.map(R32::as_f32);
+        for pos in camera.visible_images(collider) {
+            let pos = pos.as_f32();
+            self.draw_shape(
+                collider.shape(),
+                rotation,
+                color,
+                transform,
+                pos,
+                camera,
+                framebuffer,
+            );
+        }
+    }
 
-        match collider.shape {
+    #[allow(clippy::too_many_arguments)]
+    fn draw_shape(
+        &self,
+        shape: &Shape,
+        rotation: Angle<f32>,
+        color: Color,
+        transform: mat3<f32>,
+        pos: vec2<f32>,
+        camera: &Camera,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        match *shape {
             Shape::Circle { radius } => self.geng.draw2d().draw2d(
                 framebuffer,
                 camera,
@@ -227,6 +412,49 @@ impl GameRender {
                 .rotate(rotation)
                 .translate(pos),
             ),
+            Shape::Capsule {
+                half_height,
+                radius,
+            } => {
+                let (half_height, radius) = (half_height.as_f32(), radius.as_f32());
+                self.geng.draw2d().draw2d(
+                    framebuffer,
+                    camera,
+                    &draw2d::Quad::new(
+                        Aabb2::ZERO.extend_symmetric(vec2(radius, half_height)),
+                        color,
+                    )
+                    .transform(transform)
+                    .rotate(rotation)
+                    .translate(pos),
+                );
+                for end in [half_height, -half_height] {
+                    self.geng.draw2d().draw2d(
+                        framebuffer,
+                        camera,
+                        &draw2d::Ellipse::circle(vec2(0.0, end), radius, color)
+                            .transform(transform)
+                            .rotate(rotation)
+                            .translate(pos),
+                    );
+                }
+            }
+            Shape::ConvexPolygon { ref points } => self.geng.draw2d().draw2d(
+                framebuffer,
+                camera,
+                &draw2d::Polygon::new(points.iter().map(|p| p.as_f32()).collect(), color)
+                    .transform(transform)
+                    .rotate(rotation)
+                    .translate(pos),
+            ),
+            Shape::Segment { a, b, .. } => self.geng.draw2d().draw2d(
+                framebuffer,
+                camera,
+                &draw2d::Segment::new(Segment(a.as_f32(), b.as_f32()), 0.1, color)
+                    .transform(transform)
+                    .rotate(rotation)
+                    .translate(pos),
+            ),
         }
     }
 }
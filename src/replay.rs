@@ -0,0 +1,174 @@
+use crate::prelude::*;
+
+use std::path::{Path, PathBuf};
+
+/// A recording of a whole run that can be played back exactly.
+/// Keeps the config and the chunks of the run,
+/// so later changes to the assets do not change the played back world.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    /// The seed the world was generated from.
+    pub seed: u64,
+    /// The number of players.
+    pub players: usize,
+    pub config: Config,
+    pub chunks: Vec<Chunk>,
+    /// The number of simulated ticks.
+    pub ticks: u64,
+    /// Input of every player for every tick.
+    pub inputs: Vec<Vec<PlayerInput>>,
+}
+
+impl Replay {
+    /// Start recording a run of the freshly created model.
+    pub fn new(model: &Model) -> Self {
+        Self {
+            seed: model.seed,
+            players: model.players.len(),
+            config: model.config.clone(),
+            chunks: model.chunks.clone(),
+            ticks: 0,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Create the model at the start of the recorded run.
+    pub fn model(&self) -> Model {
+        Model::new(
+            self.config.clone(),
+            self.chunks.clone(),
+            self.seed,
+            self.players,
+        )
+    }
+
+    /// Whether the run was recorded with the same config and chunks as the given ones.
+    pub fn matches(&self, config: &Config, chunks: &[Chunk]) -> bool {
+        // Neither implements `PartialEq`, so compare them serialized
+        bincode::serialize(&self.config).ok() == bincode::serialize(config).ok()
+            && bincode::serialize(&self.chunks).ok() == bincode::serialize(chunks).ok()
+    }
+
+    /// Record the inputs used for the next tick.
+    pub fn record(&mut self, inputs: Vec<PlayerInput>) {
+        self.ticks += 1;
+        self.inputs.push(inputs);
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("failed to open replay at {path:?}"))?;
+        let replay: Self = bincode::deserialize_from(std::io::BufReader::new(file))
+            .with_context(|| format!("failed to parse replay at {path:?}"))?;
+        anyhow::ensure!(replay.players > 0, "replay at {path:?} has no players");
+        replay
+            .config
+            .validate()
+            .with_context(|| format!("replay at {path:?} has an invalid config"))?;
+        validate_chunks(&replay.chunks)
+            .with_context(|| format!("replay at {path:?} has invalid chunks"))?;
+        anyhow::ensure!(
+            replay.ticks == replay.inputs.len() as u64,
+            "replay at {path:?} is corrupted: expected {} ticks, found {} inputs",
+            replay.ticks,
+            replay.inputs.len()
+        );
+        for (tick, inputs) in replay.inputs.iter().enumerate() {
+            anyhow::ensure!(
+                inputs.len() == replay.players,
+                "replay at {path:?} is corrupted: tick {tick} has input for {} players instead of {}",
+                inputs.len(),
+                replay.players
+            );
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("failed to create replay at {path:?}"))?;
+        bincode::serialize_into(std::io::BufWriter::new(file), self)
+            .with_context(|| format!("failed to write replay to {path:?}"))?;
+        Ok(())
+    }
+}
+
+/// Records the input of a run and saves it to a file once the run is over,
+/// or when it is dropped, so quitting early does not lose the recording.
+pub struct Recorder {
+    replay: Replay,
+    /// Where to save the recording, `None` once it is saved.
+    path: Option<PathBuf>,
+}
+
+impl Recorder {
+    pub fn new(model: &Model, path: Option<PathBuf>) -> Self {
+        Self {
+            replay: Replay::new(model),
+            path,
+        }
+    }
+
+    /// Record the inputs used for the next tick.
+    pub fn record(&mut self, inputs: Vec<PlayerInput>) {
+        if self.path.is_some() {
+            self.replay.record(inputs);
+        }
+    }
+
+    /// Save the recording, if it has not been saved yet.
+    pub fn save(&mut self) {
+        let Some(path) = self.path.take() else {
+            return;
+        };
+        match self.replay.save(&path) {
+            Ok(()) => log::info!("Saved the replay to {path:?}"),
+            Err(err) => log::error!("{err:?}"),
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bot::Bot;
+
+    #[test]
+    fn playback_reproduces_the_run() {
+        let config = ron::from_str(include_str!("../assets/config.ron")).unwrap();
+        let chunks = ron::from_str(include_str!("../assets/chunks.ron")).unwrap();
+        let delta_time = Time::new((1.0 / crate::game::TICKS_PER_SECOND) as _);
+
+        // Let the bot play for a while
+        let mut model = Model::new(config, chunks, 9, 1);
+        let mut replay = Replay::new(&model);
+        let mut bot = Bot::new();
+        for _ in 0..1200 {
+            let inputs = vec![bot.input(&model, 0)];
+            replay.record(inputs.clone());
+            model.update(&inputs, delta_time);
+        }
+
+        // Through a file, like the game does
+        let path = std::env::temp_dir().join(format!("replay-test-{}.bin", std::process::id()));
+        replay.save(&path).unwrap();
+        let replay = Replay::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut played = replay.model();
+        for inputs in &replay.inputs {
+            played.update(inputs, delta_time);
+        }
+        let state = |model: &Model| bincode::serialize(&model.to_snapshot()).unwrap();
+        assert_eq!(state(&played), state(&model));
+    }
+}
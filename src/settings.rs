@@ -0,0 +1,197 @@
+use crate::{
+    controls::{Action, Button, Controls},
+    gamepad::Gamepads,
+    menu::*,
+    prelude::*,
+};
+
+use geng::Key;
+
+/// The key the settings are stored under.
+const SETTINGS_KEY: &str = "settings";
+
+/// Player preferences, persisted between runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    /// Volume of the sound effects, from 0 to 1.
+    pub sfx_volume: f64,
+    #[serde(default)]
+    pub controls: Controls,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            sfx_volume: 1.0,
+            controls: Controls::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let mut settings: Self = preferences::load(SETTINGS_KEY).unwrap_or_default();
+        settings.controls.bind_missing_gamepad_buttons();
+        settings
+    }
+
+    pub fn save(&self) {
+        preferences::save(SETTINGS_KEY, self);
+    }
+}
+
+/// Index of the first action in the settings menu.
+const FIRST_ACTION_OPTION: usize = 2;
+const BACK_OPTION: usize = FIRST_ACTION_OPTION + Action::ALL.len();
+
+pub struct SettingsScreen {
+    geng: Geng,
+    transition: Option<geng::state::Transition>,
+    settings: Settings,
+    menu: Menu,
+    /// The action waiting for a button to be bound to it.
+    rebinding: Option<Action>,
+    /// To bind gamepad buttons.
+    gamepads: Gamepads,
+}
+
+impl SettingsScreen {
+    pub fn new(geng: &Geng) -> Self {
+        let mut screen = Self {
+            geng: geng.clone(),
+            transition: None,
+            settings: Settings::load(),
+            menu: Menu::new(vec![String::new(); BACK_OPTION + 1]),
+            rebinding: None,
+            gamepads: Gamepads::new(),
+        };
+        screen.update_options();
+        screen
+    }
+
+    fn update_options(&mut self) {
+        let options = &mut self.menu.options;
+        options[0] = format!("Sound volume: {:.0}%", self.settings.sfx_volume * 100.0);
+        options[1] = format!(
+            "Layout: {}",
+            self.settings.controls.preset_name().unwrap_or("Custom")
+        );
+        for (i, &action) in Action::ALL.iter().enumerate() {
+            let buttons = if self.rebinding == Some(action) {
+                "press a button...".to_string()
+            } else {
+                self.settings
+                    .controls
+                    .get(action)
+                    .iter()
+                    .map(|button| button.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            options[FIRST_ACTION_OPTION + i] = format!("{}: {}", action.name(), buttons);
+        }
+        options[BACK_OPTION] = "Back".to_string();
+    }
+
+    /// Change the selected setting to the left or to the right.
+    fn change(&mut self, delta: i32) {
+        match self.menu.selected {
+            0 => {
+                self.settings.sfx_volume =
+                    (self.settings.sfx_volume + delta as f64 * 0.1).clamp(0.0, 1.0);
+            }
+            1 => {
+                let presets = &Controls::PRESETS;
+                let current = presets
+                    .iter()
+                    .position(|(_, preset)| preset() == self.settings.controls);
+                let next = match current {
+                    Some(i) => (i as i32 + delta).rem_euclid(presets.len() as i32) as usize,
+                    None => 0,
+                };
+                self.settings.controls = (presets[next].1)();
+            }
+            _ => return,
+        }
+        self.settings.save();
+        self.update_options();
+    }
+}
+
+impl geng::State for SettingsScreen {
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        let camera = geng::Camera2d {
+            fov: 14.0,
+            ..menu_camera()
+        };
+        ugli::clear(
+            framebuffer,
+            Some(BACKGROUND_COLOR.try_into().unwrap()),
+            None,
+            None,
+        );
+        self.geng.default_font().draw(
+            framebuffer,
+            &camera,
+            "Settings",
+            vec2::splat(geng::TextAlign::CENTER),
+            mat3::translate(vec2(0.0, 6.0)),
+            TEXT_COLOR.try_into().unwrap(),
+        );
+        self.menu
+            .draw(&self.geng, &camera, vec2(0.0, 4.5), framebuffer);
+    }
+
+    fn update(&mut self, _delta_time: f64) {
+        self.gamepads.poll(&self.settings.controls);
+        let Some(button) = self.gamepads.take_pressed() else {
+            return;
+        };
+        if let Some(action) = self.rebinding.take() {
+            self.settings
+                .controls
+                .rebind(action, Button::Gamepad(button));
+            self.settings.save();
+            self.update_options();
+        }
+    }
+
+    fn handle_event(&mut self, event: geng::Event) {
+        if let Some(action) = self.rebinding {
+            // Escape cancels, any other button gets bound
+            if let Some(button) = Button::from_press(&event) {
+                if button != Button::Key(Key::Escape) {
+                    self.settings.controls.rebind(action, button);
+                    self.settings.save();
+                }
+                self.rebinding = None;
+                self.update_options();
+            }
+            return;
+        }
+
+        if geng_utils::key::is_event_press(&event, [Key::A, Key::ArrowLeft]) {
+            self.change(-1);
+        }
+        if geng_utils::key::is_event_press(&event, [Key::D, Key::ArrowRight]) {
+            self.change(1);
+        }
+
+        if geng_utils::key::is_event_press(&event, [Key::Escape]) {
+            self.transition = Some(geng::state::Transition::Pop);
+            return;
+        }
+        match self.menu.handle_event(&event) {
+            Some(BACK_OPTION) => self.transition = Some(geng::state::Transition::Pop),
+            Some(i) if i >= FIRST_ACTION_OPTION => {
+                self.rebinding = Some(Action::ALL[i - FIRST_ACTION_OPTION]);
+                self.update_options();
+            }
+            _ => {}
+        }
+    }
+
+    fn transition(&mut self) -> Option<geng::state::Transition> {
+        self.transition.take()
+    }
+}